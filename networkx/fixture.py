@@ -0,0 +1,61 @@
+import heapq
+import random
+import networkx as nx
+from pathlib import Path
+
+
+# small synthetic citation graph used by the timely regression tests, together with the
+# reference result calculated by NetworkX, in the same formats as dataset/incremental and
+# yearly_result.txt
+fixture_path = Path('../timely/tests/fixtures')
+years = range(1992, 1996)
+papers_per_year = 40
+max_citations = 4
+
+if __name__ == "__main__":
+    rng = random.Random(511)
+
+    incremental_path = fixture_path / 'incremental'
+    incremental_path.mkdir(parents=True, exist_ok=True)
+
+    graph = nx.DiGraph()
+    papers = []
+    cited = {}
+
+    with open(fixture_path / 'yearly_result.txt', mode='w') as result:
+        for year in years:
+            # new papers cite earlier papers, preferring the ones already cited often
+            edges = []
+            for i in range(papers_per_year):
+                paper = int(f'{year % 100:02}{i:05}')
+                if papers:
+                    weights = [cited[p] + 1 for p in papers]
+                    k = rng.randint(0, max_citations)
+                    for dst in sorted(set(rng.choices(papers, weights=weights, k=k))):
+                        edges.append((paper, dst))
+                        cited[dst] += 1
+                cited[paper] = 0
+                papers.append(paper)
+
+            edges_path = incremental_path / f'{year}-edges.txt'
+            with open(edges_path, mode='w') as e:
+                for src, dst in edges:
+                    e.write(f'{src} {dst}\n')
+
+            graph.add_edges_from(edges)
+
+            # calculate pagerank
+            ranks = nx.pagerank(graph, alpha=0.85, tol=1e-12)
+
+            # normalize
+            numOfNodes = graph.number_of_nodes()
+            correctionFactor = numOfNodes / 1.0
+            ranks = {node: rank * correctionFactor for node, rank in ranks.items()}
+
+            topKRanks = heapq.nlargest(6, ranks.items(), key=lambda x: x[1])
+            # the test compares the top 5 in order, so they must not be tied
+            assert all(a[1] - b[1] > 1e-6 for a, b in zip(topKRanks, topKRanks[1:]))
+
+            result.write(f'--- year {year} top 5 ---\n')
+            for vert, rank in topKRanks[:5]:
+                result.write(f'{vert} has rank: {rank}\n')
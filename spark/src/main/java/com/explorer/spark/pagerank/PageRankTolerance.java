@@ -48,14 +48,45 @@ public class PageRankTolerance {
         return ranks.mapValues(v -> v * normalized_factor);
     }
 
+    private static class Max implements Function2<Double, Double, Double> {
+        final static Max INSTANCE = new Max();
+
+        @Override
+        public Double call(Double a, Double b) {
+            return Math.max(a, b);
+        }
+    }
+
+    // calculates the change between two rank vectors under the given norm, see timely/src/convergence.rs
+    private static Double Residual(JavaPairRDD<String, Double> new_ranks, JavaPairRDD<String, Double> ranks,
+            String norm) {
+        JavaRDD<Tuple2<Double, Double>> rank_pairs = new_ranks.join(ranks).values();
+        switch (norm) {
+            case "l1":
+                return rank_pairs.map(v -> Math.abs(v._1() - v._2())).reduce(Add.INSTANCE);
+            case "l2":
+                return Math.sqrt(rank_pairs.map(v -> (v._1() - v._2()) * (v._1() - v._2())).reduce(Add.INSTANCE));
+            case "linf":
+                return rank_pairs.map(v -> Math.abs(v._1() - v._2())).reduce(Max.INSTANCE);
+            case "relative":
+                return rank_pairs.map(v -> Math.abs(v._1() - v._2())).reduce(Add.INSTANCE)
+                        / rank_pairs.map(v -> Math.abs(v._1())).reduce(Add.INSTANCE);
+            default:
+                throw new IllegalArgumentException(
+                        "unknown norm '" + norm + "', expected one of: l1, l2, linf, relative");
+        }
+    }
+
     public static void main(String[] args) throws Exception {
         if (args.length < 2) {
-            System.err.println("Usage: PageRankIteration <end_year> <tolerance>");
+            System.err.println("Usage: PageRankTolerance <end_year> <tolerance> [norm] [max_iteration]");
             System.exit(1);
         }
 
         int end_year = Integer.parseInt(args[0]);
         Double tolerance = Double.parseDouble(args[1]);
+        String norm = args.length > 2 ? args[2].toLowerCase() : "linf";
+        int max_iteration = args.length > 3 ? Integer.parseInt(args[3]) : Integer.MAX_VALUE;
 
         SparkSession spark = SparkSession
                 .builder()
@@ -86,10 +117,11 @@ public class PageRankTolerance {
 
             JavaPairRDD<String, Double> base_contribs = ranks.mapValues(v -> 0.0).cache();
 
-            Double max_diff = Double.MAX_VALUE;
+            Double residual = Double.MAX_VALUE;
+            int iteration = 0;
 
             // calculates and updates ranks continuously using PageRank algorithm
-            while (max_diff > tolerance) {
+            while (residual > tolerance && iteration < max_iteration) {
                 // calculates contributions to the rank of other vertices
                 JavaPairRDD<String, Double> new_contribs = edges.join(ranks).values()
                         .flatMapToPair(verts_rank -> {
@@ -105,10 +137,10 @@ public class PageRankTolerance {
                 JavaPairRDD<String, Double> new_ranks = base_contribs.union(new_contribs).reduceByKey(Add.INSTANCE)
                         .mapValues(sum -> 0.15 + sum * 0.85);
 
-                max_diff = new_ranks.join(ranks).values().map(v -> Math.abs(v._1() - v._2()))
-                        .reduce((v1, v2) -> Math.max(v1, v2));
+                residual = Residual(new_ranks, ranks, norm);
 
                 ranks = new_ranks;
+                iteration++;
             }
 
             ranks = NormalizeRanks(ranks);
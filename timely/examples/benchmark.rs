@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::cli::{Error, Flags};
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{self, Params, WarmStart};
+use pagerank::generators::{self, Model};
+use pagerank::reference::{Deviation, ReferencePageRank};
+use pagerank::telemetry::Summary;
+
+const SEED: u64 = 0;
+const TOP_K: usize = 10;
+
+/// What worker 0 observed in one run: iteration telemetry, the final ranks and the wall-clock
+/// seconds of every epoch.
+type Observation = (Summary, BTreeMap<u64, HashMap<usize, f64>>, Vec<f64>);
+
+const USAGE: &str = "\
+usage: benchmark --model <model> --epochs <n> --scales <list> --workers <list> --tolerance <x>
+                 --iterations <n> [--report <path>]
+
+    --model <model>       erdos-renyi, barabasi-albert, rmat or citation
+    --epochs <n>          epochs of every graph
+    --scales <list>       comma-separated sizes relative to HepPh
+    --workers <list>      comma-separated numbers of workers
+    --tolerance <x>       tolerance of the LInf residual of the tolerance strategies
+    --iterations <n>      iterations per epoch of the iteration strategy
+    --report <path>       where the report goes, stdout by default";
+
+/// What to benchmark, from the command line.
+struct Options {
+    model: Model,
+    epochs: usize,
+    scales: Vec<f64>,
+    workers: Vec<usize>,
+    tolerance: f64,
+    iterations: u64,
+    report: Option<PathBuf>,
+}
+
+impl Options {
+    fn parse<I>(args: I) -> Result<Options, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let flags = Flags::parse(
+            args,
+            &[
+                "--model",
+                "--epochs",
+                "--scales",
+                "--workers",
+                "--tolerance",
+                "--iterations",
+                "--report",
+            ],
+            &[],
+        )?;
+        // every run starts its own workers
+        if let Some(arg) = flags.timely_args.get(1) {
+            return Err(Error::Invalid(format!("unknown argument '{}'", arg)));
+        }
+        Ok(Options {
+            model: flags.required("--model")?,
+            epochs: flags.required("--epochs")?,
+            scales: flags.list("--scales")?.unwrap_or_default(),
+            workers: flags.list("--workers")?.unwrap_or_default(),
+            tolerance: flags.required("--tolerance")?,
+            iterations: flags.required("--iterations")?,
+            report: flags.get("--report")?,
+        })
+    }
+}
+
+// runs every strategy on synthetic graphs of every scale with every number of workers, and writes
+// one JSON object per epoch and per run, compared against a converged sequential reference
+fn main() {
+    let Options {
+        model,
+        epochs,
+        scales,
+        workers,
+        tolerance,
+        iterations,
+        report,
+    } = match Options::parse(std::env::args()) {
+        Ok(options) => options,
+        Err(Error::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(Error::Invalid(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut report: Box<dyn Write> = match report {
+        Some(path) => Box::new(File::create(path).unwrap()),
+        None => Box::new(io::stdout()),
+    };
+
+    // iteration and incremental are what the two examples do, tolerance recomputes every epoch
+    let strategies = [
+        (
+            "iteration",
+            StopRule::iterations(iterations),
+            WarmStart::Previous,
+        ),
+        (
+            "tolerance",
+            StopRule::tolerance(Norm::LInf, tolerance),
+            WarmStart::Cold,
+        ),
+        (
+            "incremental",
+            StopRule::tolerance(Norm::LInf, tolerance),
+            WarmStart::Previous,
+        ),
+    ];
+
+    for scale in &scales {
+        // the same graph for every strategy and number of workers
+        let graph: Vec<Vec<(usize, usize)>> = generators::generator(model, *scale, SEED, 0, 1)
+            .take(epochs)
+            .collect();
+        let expected = reference_ranks(&graph);
+        let graph = Arc::new(graph);
+
+        for (strategy, stop_rule, warm_start) in strategies {
+            let params = Params::new(stop_rule).with_warm_start(warm_start);
+            for peers in &workers {
+                let (summary, ranks, latencies) = run(graph.clone(), *peers, params);
+
+                let prefix = format!(
+                    "\"strategy\":\"{}\",\"model\":\"{:?}\",\"scale\":{},\"workers\":{}",
+                    strategy, model, scale, peers
+                );
+                // the run deviates as far as its worst epoch, and by the mean of all of them
+                let mut run_deviation = Deviation {
+                    top_k_overlap: 1.0,
+                    ..Deviation::default()
+                };
+                for (epoch, latency) in latencies.iter().enumerate() {
+                    let epoch = epoch as u64;
+                    let deviation = ranks
+                        .get(&epoch)
+                        .map(|actual| Deviation::between(&expected[&epoch], actual, TOP_K))
+                        .unwrap_or_default();
+                    writeln!(
+                        report,
+                        "{{\"kind\":\"epoch\",{},\"epoch\":{},\"latency\":{},\"iterations\":{},\
+                         \"exchanged_bytes\":{},\"max_error\":{:e},\"mean_error\":{:e}}}",
+                        prefix,
+                        epoch,
+                        latency,
+                        summary.iterations(epoch),
+                        summary.exchanged_bytes(epoch),
+                        deviation.max,
+                        deviation.mean
+                    )
+                    .unwrap();
+
+                    run_deviation.max = run_deviation.max.max(deviation.max);
+                    run_deviation.mean += deviation.mean / latencies.len() as f64;
+                    run_deviation.top_k_overlap =
+                        run_deviation.top_k_overlap.min(deviation.top_k_overlap);
+                }
+
+                let exchanged_bytes: u64 = (0..epochs as u64)
+                    .map(|epoch| summary.exchanged_bytes(epoch))
+                    .sum();
+                writeln!(
+                    report,
+                    "{{\"kind\":\"run\",{},\"epochs\":{},\"latency\":{},\"iterations\":{},\
+                     \"exchanged_bytes\":{},\"max_error\":{:e},\"mean_error\":{:e},\
+                     \"top_k_overlap\":{}}}",
+                    prefix,
+                    epochs,
+                    latencies.iter().sum::<f64>(),
+                    summary.total_iterations(),
+                    exchanged_bytes,
+                    run_deviation.max,
+                    run_deviation.mean,
+                    run_deviation.top_k_overlap
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+/// Runs one strategy on `graph` with `peers` workers, which split the edges of every epoch.
+fn run(graph: Arc<Vec<Vec<(usize, usize)>>>, peers: usize, params: Params) -> Observation {
+    let guards = timely::execute(Config::process(peers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let summary = Rc::new(RefCell::new(Summary::new()));
+        let results = Rc::new(RefCell::new(BTreeMap::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, stats) = dataflow::pagerank(&edge_stream, params);
+
+            let summary = summary.clone();
+            stats.inspect_batch(move |time, data| {
+                for stats in data {
+                    summary.borrow_mut().record(*time, *stats);
+                }
+            });
+
+            // gather all ranks on worker 0
+            let results = results.clone();
+            ranks
+                .exchange(|_| 0)
+                .inspect_batch(move |time, data| {
+                    let mut results = results.borrow_mut();
+                    let epoch_ranks = results.entry(*time).or_insert(HashMap::new());
+                    for (vert, rank) in data {
+                        epoch_ranks.insert(*vert, *rank);
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        // latency covers feeding the epoch until its final ranks are out
+        let mut latencies = Vec::new();
+        for (epoch, edges) in graph.iter().enumerate() {
+            let start = Instant::now();
+            for e in edges.iter().skip(worker.index()).step_by(worker.peers()) {
+                input.send(*e);
+            }
+            input.advance_to(epoch as u64 + 1);
+
+            worker.step_while(|| probe.less_than(input.time()));
+            latencies.push(start.elapsed().as_secs_f64());
+        }
+
+        let summary = summary.borrow().clone();
+        let results = results.borrow().clone();
+        (summary, results, latencies)
+    })
+    .unwrap();
+
+    // only worker 0 collects the telemetry and the ranks
+    guards.join().remove(0).unwrap()
+}
+
+/// Converged ranks of every epoch, to measure the error of every strategy against.
+fn reference_ranks(graph: &[Vec<(usize, usize)>]) -> BTreeMap<u64, HashMap<usize, f64>> {
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-10).with_max_iterations(1000);
+    let mut reference = ReferencePageRank::new(Params::new(stop_rule));
+    graph
+        .iter()
+        .enumerate()
+        .map(|(epoch, edges)| (epoch as u64, reference.advance(edges)))
+        .collect()
+}
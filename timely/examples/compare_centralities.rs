@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{self, Centrality, Params};
+use pagerank::reference::Deviation;
+use pagerank::utils;
+
+const TOP_K: usize = 5;
+
+const CENTRALITIES: [Centrality; 3] = [
+    Centrality::PageRank,
+    Centrality::Katz {
+        alpha: 0.1,
+        beta: 1.0,
+    },
+    Centrality::Eigenvector,
+];
+
+// ranks the same years with every centrality in one dataflow, and reports how far katz and eigenvector centrality are from pagerank
+fn main() {
+    let end_year: u64 = utils::positional_arg(1).expect("the last year is required");
+    let tolerance: f64 = utils::positional_arg(2).expect("the tolerance is required");
+    // eigenvector centrality converges slowly on citations, which never form cycles
+    let max_iterations: u64 = utils::positional_arg(3).unwrap_or(100);
+    let stop_rule =
+        StopRule::tolerance(Norm::Relative, tolerance).with_max_iterations(max_iterations);
+    // the first year to report, with the years before it loaded at once when asked for
+    let (start_year, snapshot) = utils::start_from_args();
+
+    let guards = timely::execute_from_args(utils::timely_args(), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(BTreeMap::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+
+            for (position, centrality) in CENTRALITIES.into_iter().enumerate() {
+                let params = Params::new(stop_rule).with_centrality(centrality);
+                let (ranks, _stats) = dataflow::pagerank(&edge_stream, params);
+
+                // gather all ranks on worker 0
+                let results = results.clone();
+                ranks
+                    .exchange(|_| 0)
+                    .inspect_batch(move |time, data| {
+                        let mut results = results.borrow_mut();
+                        let year_ranks = results.entry((*time, position)).or_insert(HashMap::new());
+                        for (vert, rank) in data {
+                            year_ranks.insert(*vert, *rank);
+                        }
+                    })
+                    .probe_with(&mut probe);
+            }
+        });
+
+        // feeding edges, every worker its own share of every year
+        input.advance_to(start_year);
+        let (index, peers) = (worker.index(), worker.peers());
+        let years = utils::get_year_shards_from_file(start_year, end_year, snapshot, index, peers)
+            .expect("cannot read the dataset");
+        utils::feed_years(worker, &mut input, &probe, years);
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    // only worker 0 collects the ranks
+    let ranks = guards.join().remove(0).unwrap();
+
+    println!(
+        "{:>6} {:>12} {:>14} {:>8} {:>12} {:>14} {:>8}",
+        "year", "katz max", "katz mean", "top 5", "eigen max", "eigen mean", "top 5"
+    );
+    for year in start_year..=end_year {
+        let pagerank = &ranks[&(year, 0)];
+        print!("{:>6}", year);
+        for position in 1..CENTRALITIES.len() {
+            let deviation = Deviation::between(pagerank, &ranks[&(year, position)], TOP_K);
+            print!(
+                " {:>12.4} {:>14.6} {:>7.0}%",
+                deviation.max,
+                deviation.mean,
+                100.0 * deviation.top_k_overlap
+            );
+        }
+        println!();
+    }
+}
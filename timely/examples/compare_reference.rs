@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{self, Params};
+use pagerank::reference::{Deviation, ReferencePageRank};
+use pagerank::utils;
+
+const TOP_K: usize = 5;
+
+// runs the dataflow and the sequential reference on the same years, and reports how far apart their ranks are
+fn main() {
+    let end_year: u64 = std::env::args().nth(1).unwrap().parse().unwrap();
+    let tolerance: f64 = std::env::args().nth(2).unwrap().parse().unwrap();
+    let norm: Norm = std::env::args()
+        .nth(3)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| arg.parse().unwrap())
+        .unwrap_or(Norm::LInf);
+    let stop_rule = StopRule::tolerance(norm, tolerance);
+    // the first year to report, with the years before it loaded at once when asked for
+    let (start_year, snapshot) = utils::start_from_args();
+
+    let guards = timely::execute_from_args(utils::timely_args(), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(BTreeMap::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = dataflow::pagerank(&edge_stream, Params::new(stop_rule));
+
+            // gather all ranks on worker 0
+            let results = results.clone();
+            ranks
+                .exchange(|_| 0)
+                .inspect_batch(move |time, data| {
+                    let mut results = results.borrow_mut();
+                    let year_ranks = results.entry(*time).or_insert(HashMap::new());
+                    for (vert, rank) in data {
+                        year_ranks.insert(*vert, *rank);
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        // feeding edges
+        input.advance_to(start_year);
+        if worker.index() == 0 {
+            for (year, edges) in utils::get_years_from_file(start_year, end_year, snapshot)
+                .expect("cannot read the dataset")
+            {
+                for e in edges {
+                    input.send(e);
+                }
+                input.advance_to(year + 1);
+
+                worker.step_while(|| probe.less_than(input.time()));
+            }
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    // only worker 0 collects the ranks
+    let dataflow_ranks = guards.join().remove(0).unwrap();
+
+    let mut reference = ReferencePageRank::new(Params::new(stop_rule));
+    println!(
+        "{:>6} {:>10} {:>14} {:>14} {:>8}",
+        "year", "iterations", "max dev", "mean dev", "top 5"
+    );
+    for (year, edges) in
+        utils::get_years_from_file(start_year, end_year, snapshot).expect("cannot read the dataset")
+    {
+        let expected = reference.advance(&edges);
+        if let Some(actual) = dataflow_ranks.get(&year) {
+            let deviation = Deviation::between(&expected, actual, TOP_K);
+            println!(
+                "{:>6} {:>10} {:>14.6e} {:>14.6e} {:>7.0}%",
+                year,
+                reference.iterations(),
+                deviation.max,
+                deviation.mean,
+                100.0 * deviation.top_k_overlap
+            );
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, Schedule};
+use pagerank::utils;
+
+// savings are relative to the synchronous schedule, which comes first
+const SCHEDULES: [Schedule; 3] = [Schedule::Jacobi, Schedule::GaussSeidel, Schedule::Blocks];
+
+// runs the tolerance approach once per schedule, and reports how many iterations each schedule needs per year
+fn main() {
+    let end_year: u64 = utils::positional_arg(1).expect("the last year is required");
+    let tolerance: f64 = utils::positional_arg(2).expect("the tolerance is required");
+    let norm: Norm = utils::positional_arg(3).unwrap_or(Norm::LInf);
+    let stop_rule = StopRule::tolerance(norm, tolerance);
+
+    utils::compare_iterations(&SCHEDULES, end_year, move |schedule| {
+        Params::new(stop_rule).with_schedule(schedule)
+    });
+}
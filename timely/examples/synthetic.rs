@@ -0,0 +1,61 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{self, Params};
+use pagerank::generators::{self, Model};
+use pagerank::telemetry::Summary;
+
+// runs the tolerance approach on a synthetic graph, scaled relative to the HepPh dataset
+fn main() {
+    timely::execute_from_args(std::env::args(), |worker| {
+        let model: Model = std::env::args().nth(1).unwrap().parse().unwrap();
+        let epochs: u64 = std::env::args().nth(2).unwrap().parse().unwrap();
+        let scale: f64 = std::env::args().nth(3).unwrap().parse().unwrap();
+        let tolerance: f64 = std::env::args().nth(4).unwrap().parse().unwrap();
+        let seed: u64 = std::env::args()
+            .nth(5)
+            .filter(|arg| !arg.starts_with('-'))
+            .map(|arg| arg.parse().unwrap())
+            .unwrap_or(0);
+        let stop_rule = StopRule::tolerance(Norm::LInf, tolerance);
+
+        // input is a stream of edges in the format of (from, to)
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let summary = Rc::new(RefCell::new(Summary::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, stats) = dataflow::pagerank(&edge_stream, Params::new(stop_rule));
+
+            let summary = summary.clone();
+            stats.inspect_batch(move |time, data| {
+                for stats in data {
+                    summary.borrow_mut().record(*time, *stats);
+                }
+            });
+            dataflow::print_top_ranks(&ranks, 5).probe_with(&mut probe);
+        });
+
+        // every worker generates and feeds its own share of the edges
+        let mut generator =
+            generators::generator(model, scale, seed, worker.index(), worker.peers());
+        for epoch in 0..epochs {
+            for e in generator.next().unwrap() {
+                input.send(e);
+            }
+            input.advance_to(epoch + 1);
+
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        if worker.index() == 0 {
+            summary.borrow().print(stop_rule.norm);
+        }
+    })
+    .unwrap();
+}
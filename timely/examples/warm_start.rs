@@ -0,0 +1,23 @@
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, WarmStart};
+use pagerank::utils;
+
+// savings are relative to starting every year from scratch, which comes first
+const MODES: [WarmStart; 4] = [
+    WarmStart::Cold,
+    WarmStart::Previous,
+    WarmStart::Mean,
+    WarmStart::Teleport,
+];
+
+// runs the tolerance approach once per warm start mode, and reports how many iterations each mode needs per year
+fn main() {
+    let end_year: u64 = utils::positional_arg(1).expect("the last year is required");
+    let tolerance: f64 = utils::positional_arg(2).expect("the tolerance is required");
+    let norm: Norm = utils::positional_arg(3).unwrap_or(Norm::LInf);
+    let stop_rule = StopRule::tolerance(norm, tolerance);
+
+    utils::compare_iterations(&MODES, end_year, move |warm_start| {
+        Params::new(stop_rule).with_warm_start(warm_start)
+    });
+}
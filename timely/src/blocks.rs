@@ -0,0 +1,609 @@
+use std::collections::{HashMap, HashSet};
+
+use abomonation_derive::Abomonation;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::{operators::*, Scope, Stream};
+
+use crate::convergence::{Residual, StopRule};
+use crate::dataflow::{self, Params, Teleport, WarmStart};
+use crate::telemetry::IterationStats;
+
+/// A vertex as worker 0 hands it out, with everything the worker ranking it needs to know.
+#[derive(Abomonation, Clone, Debug)]
+struct Member {
+    vertex: usize,
+    /// where the epoch starts from, as the warm start says
+    rank: f64,
+    age: u64,
+    share: f64,
+    total_weight: f64,
+    /// the vertices it cites, along with the worker ranking them and the weight of the citation
+    cites: Vec<(usize, usize, f64)>,
+}
+
+/// A strongly connected block of the citation graph, the worker ranking it, and its position in
+/// the topological order of all blocks, where every block comes before the blocks it cites.
+#[derive(Abomonation, Clone, Debug)]
+struct Block {
+    worker: usize,
+    position: usize,
+    /// whether its members cite each other, so that it takes more than one sweep to rank
+    cyclic: bool,
+    members: Vec<Member>,
+}
+
+/// Records going around the loop of the `BlockRanks` operator.
+#[derive(Abomonation, Clone, Debug)]
+enum Message {
+    /// how much the `(owner, dst, contribution)` to `dst` changed, summed up by worker `owner`
+    Contribution(usize, usize, f64),
+    /// every worker asks itself to sweep its blocks once they are all there
+    Sweep(usize),
+}
+
+impl Message {
+    fn owner(&self) -> usize {
+        match *self {
+            Message::Contribution(owner, _, _) => owner,
+            Message::Sweep(index) => index,
+        }
+    }
+}
+
+/// Records produced by the `BlockRanks` operator in each iteration.
+#[derive(Clone, Debug)]
+enum Update {
+    Message(Message),
+    /// the `(vertex, rank, iteration)` of a vertex whose rank changed
+    Rank(usize, f64, u64),
+    /// how much the ranks held by one worker changed, and how many contributions it sent to
+    /// other workers
+    Progress(Residual, u64),
+}
+
+/// Calculates the same ranks as `dataflow::personalized_pagerank_from`, block by block: worker 0
+/// gathers the citation graph, finds its strongly connected blocks, and hands out consecutive runs
+/// of them in topological order to the workers, so that citations only go from one worker to the
+/// ones after it. Every worker ranks its blocks in order, each from the final ranks of the blocks
+/// citing it, and iterates within a block only where its members cite each other, until
+/// `params.stop_rule` holds for the block. An epoch takes as many iterations as there are workers
+/// at most, at the cost of holding the graph on worker 0, which keeps the blocks up to date as
+/// citations come in and hands all of them out every epoch while the other workers wait, and of a
+/// worker ranking a whole block. As the stop rule holds for every block on its own, the ranks are
+/// only as close to those of the other schedules as it says, not the same after as many iterations.
+pub fn block_pagerank<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    teleport: Teleport,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    let stop_rule = params.stop_rule;
+    let mut scope = edges.scope();
+
+    // every epoch starts from the final ranks of the one before
+    let (handle, previous_ranks) = scope.feedback(1);
+    let blocks = hand_out(edges, &previous_ranks, params, teleport);
+
+    let (ranks, stats) = scope.iterative::<u64, _, _>(|subscope| {
+        let (handle, message_stream) = subscope.loop_variable(1);
+        let index = subscope.index();
+
+        let updates = blocks.enter(subscope).binary_frontier(
+            &message_stream,
+            Exchange::new(|block: &Block| block.worker as u64),
+            Exchange::new(|message: &Message| message.owner() as u64),
+            "BlockRanks",
+            move |_capability, _info| {
+                // where we stash out-of-order data
+                let mut block_stash = HashMap::new();
+                let mut message_stash = HashMap::new();
+
+                // empty list for swapping
+                let mut block_vec = Vec::new();
+                let mut message_vec = Vec::new();
+
+                // the blocks of the epoch being ranked
+                let mut local = Local::default();
+
+                move |input1, input2, output| {
+                    input1.for_each(|time, data| {
+                        data.swap(&mut block_vec);
+                        block_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut block_vec);
+                    });
+                    input2.for_each(|time, data| {
+                        data.swap(&mut message_vec);
+                        message_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut message_vec);
+                    });
+
+                    let frontiers = &[input1.frontier(), input2.frontier()];
+
+                    // an epoch starts once all blocks of this worker are there
+                    for (time, blocks) in block_stash.iter_mut() {
+                        if frontiers.iter().all(|f| !f.less_equal(time)) {
+                            let mut session = output.session(time);
+                            local = Local::new(std::mem::take(blocks), &params, index);
+
+                            let mut residual = Residual::default();
+                            for (vert, rank) in local.ranks.iter() {
+                                residual.add(*rank, *rank);
+                                session.give(Update::Rank(*vert, *rank, time.inner));
+                            }
+                            let exchanged = local.outgoing.len() as u64;
+                            for ((owner, dst), change) in local.outgoing.drain() {
+                                let message = Message::Contribution(owner, dst, change);
+                                session.give(Update::Message(message));
+                            }
+                            session.give(Update::Message(Message::Sweep(index)));
+                            session.give(Update::Progress(residual, exchanged));
+                        }
+                    }
+                    block_stash.retain(|_key, val| !val.is_empty());
+
+                    // sweep again whenever the contributions of other workers changed
+                    for (time, messages) in message_stash.iter_mut() {
+                        if frontiers.iter().all(|f| !f.less_equal(time)) {
+                            let mut session = output.session(time);
+                            for message in messages.drain(..) {
+                                if let Message::Contribution(_, dst, change) = message {
+                                    *local.external.entry(dst).or_insert(0.0f64) += change;
+                                }
+                            }
+
+                            let (residual, changed) = local.sweep(&params, stop_rule, index);
+                            for (vert, rank) in changed {
+                                session.give(Update::Rank(vert, rank, time.inner));
+                            }
+                            let exchanged = local.outgoing.len() as u64;
+                            for ((owner, dst), change) in local.outgoing.drain() {
+                                let message = Message::Contribution(owner, dst, change);
+                                session.give(Update::Message(message));
+                            }
+                            session.give(Update::Progress(residual, exchanged));
+                        }
+                    }
+                    message_stash.retain(|_key, val| !val.is_empty());
+                }
+            },
+        );
+
+        let messages = updates.flat_map(|update| match update {
+            Update::Message(message) => Some(message),
+            _ => None,
+        });
+        let ranks = updates.flat_map(|update| match update {
+            Update::Rank(vert, rank, iteration) => Some((vert, rank, iteration)),
+            _ => None,
+        });
+        let local_residual = updates.flat_map(|update| match update {
+            Update::Progress(residual, exchanged) => Some((residual, exchanged)),
+            _ => None,
+        });
+
+        let global_residual = dataflow::all_reduce(&local_residual);
+        let stats = dataflow::telemetry(&global_residual, index);
+        // changes left once the stop rule holds are dropped, like the iterations they would start
+        dataflow::continuing(&messages, &global_residual, stop_rule).connect_loop(handle);
+
+        (ranks.leave(), stats.leave())
+    });
+
+    let ranks = latest_ranks(&ranks);
+    ranks.connect_loop(handle);
+    (ranks, stats)
+}
+
+/// The blocks one worker ranks in an epoch, in topological order, along with the contributions to
+/// every vertex of the vertices citing it, summed up as they change.
+#[derive(Default)]
+struct Local {
+    blocks: Vec<Block>,
+    ranks: HashMap<usize, f64>,
+    /// from the vertices on other workers
+    external: HashMap<usize, f64>,
+    /// from the vertices on this worker
+    internal: HashMap<usize, f64>,
+    /// changes of the contributions to vertices on other workers, not sent yet
+    outgoing: HashMap<(usize, usize), f64>,
+}
+
+impl Local {
+    fn new(mut blocks: Vec<Block>, params: &Params, index: usize) -> Self {
+        blocks.sort_by_key(|block| block.position);
+        let mut local = Local::default();
+        for member in blocks.iter().flat_map(|block| &block.members) {
+            local.ranks.insert(member.vertex, member.rank);
+            local.pass_on(member, member.rank, params, index);
+        }
+        local.blocks = blocks;
+        local
+    }
+
+    /// Adds the contributions of `member` for a change of its rank by `change` to the vertices it
+    /// cites.
+    fn pass_on(&mut self, member: &Member, change: f64, params: &Params, index: usize) {
+        for (dst, owner, weight) in &member.cites {
+            let contribution = params.contribution(change, *weight, member.total_weight);
+            if *owner == index {
+                *self.internal.entry(*dst).or_insert(0.0f64) += contribution;
+            } else {
+                *self.outgoing.entry((*owner, *dst)).or_insert(0.0f64) += contribution;
+            }
+        }
+    }
+
+    /// Ranks every block in order, sweeping over the members of cyclic blocks until `stop_rule`
+    /// holds for the block. Returns how much the ranks changed, and the vertices whose rank did.
+    fn sweep(
+        &mut self,
+        params: &Params,
+        stop_rule: StopRule,
+        index: usize,
+    ) -> (Residual, Vec<(usize, f64)>) {
+        let old_ranks = self.ranks.clone();
+        let blocks = std::mem::take(&mut self.blocks);
+        for block in &blocks {
+            let mut sweeps = 0;
+            loop {
+                sweeps += 1;
+                let mut block_residual = Residual::default();
+                for member in &block.members {
+                    let contribution = self.external.get(&member.vertex).unwrap_or(&0.0f64)
+                        + self.internal.get(&member.vertex).unwrap_or(&0.0f64);
+                    let new_rank = params.rank(contribution, member.age, member.share);
+                    let old_rank = self.ranks.insert(member.vertex, new_rank).unwrap();
+                    block_residual.add(new_rank, old_rank);
+                    if new_rank != old_rank {
+                        self.pass_on(member, new_rank - old_rank, params, index);
+                    }
+                }
+                if !block.cyclic || !stop_rule.should_continue(sweeps, &block_residual) {
+                    break;
+                }
+            }
+        }
+        self.blocks = blocks;
+
+        let mut residual = Residual::default();
+        let mut changed = Vec::new();
+        for (vert, rank) in self.ranks.iter() {
+            let old_rank = old_ranks[vert];
+            residual.add(*rank, old_rank);
+            if *rank != old_rank {
+                changed.push((*vert, *rank));
+            }
+        }
+        (residual, changed)
+    }
+}
+
+/// Hands out the blocks of each epoch of `edges` on worker 0, starting from the `previous_ranks`,
+/// the final ranks of the epoch before.
+fn hand_out<G>(
+    edges: &Stream<G, (usize, usize)>,
+    previous_ranks: &Stream<G, (usize, f64)>,
+    params: Params,
+    teleport: Teleport,
+) -> Stream<G, Block>
+where
+    G: Scope<Timestamp = u64>,
+{
+    let peers = edges.scope().peers();
+    edges.binary_frontier(
+        previous_ranks,
+        Exchange::new(|_| 0),
+        Exchange::new(|_| 0),
+        "Blocks",
+        move |_capability, _info| {
+            let mut graph = Graph::default();
+            let mut edge_stash = HashMap::new();
+            let mut edge_vec = Vec::new();
+            let mut rank_vec = Vec::new();
+
+            move |input1, input2, output| {
+                input1.for_each(|time, data| {
+                    data.swap(&mut edge_vec);
+                    edge_stash
+                        .entry(time.retain())
+                        .or_insert(Vec::new())
+                        .append(&mut edge_vec);
+                });
+                input2.for_each(|_time, data| {
+                    data.swap(&mut rank_vec);
+                    for (vert, rank) in rank_vec.drain(..) {
+                        graph.set_rank(vert, rank);
+                    }
+                });
+
+                // the ranks of the epoch before are all there once it stopped iterating
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                for (time, edges) in edge_stash.iter_mut() {
+                    if frontiers.iter().all(|f| !f.less_equal(time)) {
+                        let epoch = *time.time();
+                        graph.add(edges.drain(..), epoch, &params);
+                        let mut session = output.session(time);
+                        for block in graph.blocks(epoch, peers, &params, &teleport) {
+                            session.give(block);
+                        }
+                    }
+                }
+                edge_stash.retain(|_key, val| !val.is_empty());
+            }
+        },
+    )
+}
+
+/// The rank each vertex had in the last iteration it changed in, once its epoch stopped iterating,
+/// on the worker owning the vertex.
+fn latest_ranks<G>(ranks: &Stream<G, (usize, f64, u64)>) -> Stream<G, (usize, f64)>
+where
+    G: Scope<Timestamp = u64>,
+{
+    ranks.unary_frontier(
+        Exchange::new(|x: &(usize, f64, u64)| x.0 as u64),
+        "LatestRanks",
+        |_capability, _info| {
+            let mut rank_stash = HashMap::new();
+            let mut rank_vec = Vec::new();
+
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut rank_vec);
+                    let latest = rank_stash.entry(time.retain()).or_insert(HashMap::new());
+                    for (vert, rank, iteration) in rank_vec.drain(..) {
+                        let (latest_iteration, latest_rank) =
+                            latest.entry(vert).or_insert((iteration, rank));
+                        if iteration >= *latest_iteration {
+                            *latest_iteration = iteration;
+                            *latest_rank = rank;
+                        }
+                    }
+                });
+
+                for (time, latest) in rank_stash.iter_mut() {
+                    if !input.frontier().less_equal(time) {
+                        let mut session = output.session(time);
+                        for (vert, (_, rank)) in latest.drain() {
+                            session.give((vert, rank));
+                        }
+                    }
+                }
+                rank_stash.retain(|_key, val| !val.is_empty());
+            }
+        },
+    )
+}
+
+/// The whole citation graph over dense vertex indices, on worker 0, along with its strongly
+/// connected components, which every citation updates as it is added.
+#[derive(Default)]
+struct Graph {
+    index: HashMap<usize, usize>,
+    verts: Vec<usize>,
+    /// the cited vertices of every vertex, along with the epoch each citation was added in
+    cites: Vec<Vec<(usize, u64)>>,
+    cited_by: Vec<Vec<usize>>,
+    ranks: Vec<f64>,
+    born: Vec<u64>,
+    /// the component of every vertex
+    component: Vec<usize>,
+    /// the vertices of every component, none once it was merged into another one
+    members: Vec<Vec<usize>>,
+    /// the position of every component in the topological order, where every component comes
+    /// before the components it cites, and new components before all others
+    key: Vec<i64>,
+    first_key: i64,
+}
+
+impl Graph {
+    fn set_rank(&mut self, vert: usize, rank: f64) {
+        if let Some(index) = self.index.get(&vert) {
+            self.ranks[*index] = rank;
+        }
+    }
+
+    /// Adds the `edges` of `epoch`, deciding where known and new vertices start from.
+    fn add<I>(&mut self, edges: I, epoch: u64, params: &Params)
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        let initial_rank = match params.warm_start {
+            WarmStart::Cold => {
+                self.ranks.iter_mut().for_each(|rank| *rank = 1.0);
+                1.0
+            }
+            WarmStart::Previous => 1.0,
+            WarmStart::Mean if !self.ranks.is_empty() => {
+                self.ranks.iter().sum::<f64>() / (self.ranks.len() as f64)
+            }
+            WarmStart::Mean => 1.0,
+            WarmStart::Teleport => params.teleport_start(),
+        };
+
+        for (src, dst) in edges {
+            let src = self.vertex(src, epoch, initial_rank);
+            let dst = self.vertex(dst, epoch, initial_rank);
+            self.cite(src, dst, epoch);
+        }
+    }
+
+    fn vertex(&mut self, vert: usize, epoch: u64, initial_rank: f64) -> usize {
+        let next = self.verts.len();
+        let index = *self.index.entry(vert).or_insert(next);
+        if index == next {
+            self.verts.push(vert);
+            self.cites.push(Vec::new());
+            self.cited_by.push(Vec::new());
+            self.ranks.push(initial_rank);
+            self.born.push(epoch);
+
+            // papers mostly cite older papers, so a new one goes before all others
+            self.first_key -= 1;
+            self.component.push(self.members.len());
+            self.members.push(vec![index]);
+            self.key.push(self.first_key);
+        }
+        index
+    }
+
+    /// Adds the citation of `dst` by `src`, which merges the components it closes a cycle through
+    /// and moves the components it puts out of order, as the algorithm of Pearce and Kelly does,
+    /// so that only the components between those of `dst` and `src` in the order are visited.
+    fn cite(&mut self, src: usize, dst: usize, epoch: u64) {
+        self.cites[src].push((dst, epoch));
+        self.cited_by[dst].push(src);
+
+        let (from, to) = (self.component[src], self.component[dst]);
+        if from == to || self.key[from] < self.key[to] {
+            return;
+        }
+        let (lower, upper) = (self.key[to], self.key[from]);
+        let forward = self.reachable(to, true, |key| key <= upper);
+        let backward = self.reachable(from, false, |key| key >= lower);
+
+        // what `to` reaches and reaches `from` as well is a cycle now, whose components come after
+        // what only reaches `from`, and before what `to` only reaches
+        let mut keys = forward
+            .iter()
+            .chain(&backward)
+            .map(|component| self.key[*component])
+            .collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys.dedup();
+        let mut before = backward
+            .iter()
+            .filter(|component| !forward.contains(component))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut after = forward
+            .iter()
+            .filter(|component| !backward.contains(component))
+            .copied()
+            .collect::<Vec<_>>();
+        let mut cycle = forward
+            .iter()
+            .filter(|component| backward.contains(component))
+            .copied()
+            .collect::<Vec<_>>();
+        before.sort_by_key(|component| self.key[*component]);
+        after.sort_by_key(|component| self.key[*component]);
+        cycle.sort_unstable();
+
+        let merged = cycle.first().copied();
+        for component in cycle.iter().skip(1) {
+            for vert in std::mem::take(&mut self.members[*component]) {
+                self.component[vert] = cycle[0];
+                self.members[cycle[0]].push(vert);
+            }
+        }
+        for (component, key) in before.into_iter().chain(merged).chain(after).zip(keys) {
+            self.key[component] = key;
+        }
+    }
+
+    /// The components reachable from `start`, following citations `forward` or the other way
+    /// around, through components whose key is `within` bounds only.
+    fn reachable<F>(&self, start: usize, forward: bool, within: F) -> HashSet<usize>
+    where
+        F: Fn(i64) -> bool,
+    {
+        let mut reached = HashSet::new();
+        reached.insert(start);
+        let mut stack = vec![start];
+        while let Some(component) = stack.pop() {
+            for vert in &self.members[component] {
+                let cites = self.cites[*vert].iter().map(|(dst, _)| dst);
+                let cited_by = self.cited_by[*vert].iter();
+                let neighbors = cites
+                    .filter(|_| forward)
+                    .chain(cited_by.filter(|_| !forward));
+                for neighbor in neighbors {
+                    let next = self.component[*neighbor];
+                    if within(self.key[next]) && reached.insert(next) {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        reached
+    }
+
+    /// The blocks of `epoch`, handed out to `peers` workers in consecutive runs of about the same
+    /// number of vertices and citations.
+    fn blocks(&self, epoch: u64, peers: usize, params: &Params, teleport: &Teleport) -> Vec<Block> {
+        let mut components = (0..self.members.len())
+            .filter(|component| !self.members[*component].is_empty())
+            .collect::<Vec<_>>();
+        components.sort_by_key(|component| self.key[*component]);
+        let components = components
+            .into_iter()
+            .map(|component| &self.members[component])
+            .collect::<Vec<_>>();
+        let sizes = components
+            .iter()
+            .map(|component| {
+                component
+                    .iter()
+                    .map(|vert| 1 + self.cites[*vert].len())
+                    .sum::<usize>()
+            })
+            .collect::<Vec<_>>();
+        let total = sizes.iter().sum::<usize>().max(1);
+
+        let mut owners = vec![0; self.verts.len()];
+        let mut workers = Vec::with_capacity(components.len());
+        let mut before = 0;
+        for (component, size) in components.iter().zip(&sizes) {
+            let worker = (before * peers / total).min(peers - 1);
+            for vert in component.iter() {
+                owners[*vert] = worker;
+            }
+            workers.push(worker);
+            before += size;
+        }
+
+        components
+            .into_iter()
+            .zip(workers)
+            .enumerate()
+            .map(|(position, (component, worker))| {
+                let first = component[0];
+                let cyclic = component.len() > 1 || self.cites[first].iter().any(|c| c.0 == first);
+                let members = component
+                    .iter()
+                    .map(|&vert| {
+                        let weights = self.cites[vert]
+                            .iter()
+                            .map(|(_, added)| params.decay.weight(epoch - added))
+                            .collect::<Vec<_>>();
+                        Member {
+                            vertex: self.verts[vert],
+                            rank: self.ranks[vert],
+                            age: epoch - self.born[vert],
+                            share: teleport.share(self.verts[vert]),
+                            total_weight: weights.iter().sum(),
+                            cites: self.cites[vert]
+                                .iter()
+                                .zip(weights)
+                                .map(|((dst, _), weight)| (self.verts[*dst], owners[*dst], weight))
+                                .collect(),
+                        }
+                    })
+                    .collect();
+                Block {
+                    worker,
+                    position,
+                    cyclic,
+                    members,
+                }
+            })
+            .collect()
+    }
+}
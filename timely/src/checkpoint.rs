@@ -0,0 +1,217 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::dataflow::State;
+
+/// A directory holding the `State` of every worker after each completed epoch, in a subdirectory
+/// per epoch with one plain-text file per worker:
+///
+/// ```text
+/// <epoch> <worker index> <number of workers>
+/// r <vertex> <rank>
+/// b <vertex> <epoch it first appeared in>
+/// e <vertex> <cited vertex>@<epoch cited> <cited vertex>@<epoch cited> ...
+/// ```
+///
+/// Every worker writes its own file, to a temporary file first, so an epoch only counts as saved
+/// once the files of all workers are there. Older epochs are kept, to resume from any of them.
+/// Along with them, worker 0 saves the `Config` of the run as `config.toml`, if it has one, so that
+/// a run only continues from the state of the same measure on the same input, see
+/// `Config::differs_in_state`.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    dir: PathBuf,
+    config: Option<Config>,
+}
+
+impl Checkpoint {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Checkpoint {
+            dir: dir.as_ref().to_path_buf(),
+            config: None,
+        }
+    }
+
+    /// Saves `config` along with every epoch, as the run the state belongs to.
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Saves the `state` worker `index` of `peers` holds once `epoch` completed.
+    pub fn save(&self, epoch: u64, index: usize, peers: usize, state: &State) -> io::Result<()> {
+        let dir = self.dir.join(epoch.to_string());
+        fs::create_dir_all(&dir)?;
+
+        // before the worker file, so that a complete epoch always says which run saved it
+        if let (0, Some(config)) = (index, &self.config) {
+            let tmp_path = dir.join("config.toml.tmp");
+            fs::write(&tmp_path, config.to_toml())?;
+            fs::rename(tmp_path, dir.join("config.toml"))?;
+        }
+
+        let tmp_path = dir.join(format!("worker-{}.tmp", index));
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writeln!(writer, "{} {} {}", epoch, index, peers)?;
+        for (vert, rank) in &state.ranks {
+            // `{:?}` prints the shortest representation that parses back to the same rank
+            writeln!(writer, "r {} {:?}", vert, rank)?;
+        }
+        for (vert, born) in &state.born {
+            writeln!(writer, "b {} {}", vert, born)?;
+        }
+        for (vert, dsts) in &state.edges {
+            write!(writer, "e {}", vert)?;
+            for (dst, added) in dsts {
+                write!(writer, " {}@{}", dst, added)?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(tmp_path, dir.join(format!("worker-{}.txt", index)))
+    }
+
+    /// The config of the run that saved `epoch`, if it saved one.
+    pub fn config(&self, epoch: u64) -> io::Result<Option<Config>> {
+        let path = self.dir.join(epoch.to_string()).join("config.toml");
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Config::load(&path)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// The last epoch the files of all workers were saved for, if any.
+    pub fn latest(&self) -> io::Result<Option<u64>> {
+        let mut epochs = Vec::new();
+        if self.dir.is_dir() {
+            for entry in fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                if let Some(epoch) = entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+                    epochs.push(epoch);
+                }
+            }
+        }
+        epochs.sort_unstable();
+
+        for epoch in epochs.into_iter().rev() {
+            if self.is_complete(epoch)? {
+                return Ok(Some(epoch));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Loads the part of the state saved after `epoch` that worker `index` of `peers` owns. The
+    /// number of workers may differ from the run that saved it, vertices are owned by the worker
+    /// they hash to either way.
+    pub fn load(&self, epoch: u64, index: usize, peers: usize) -> io::Result<State> {
+        let mut state = State::default();
+        let owned = |vert: usize| vert % peers == index;
+
+        for path in self.worker_files(epoch)? {
+            let reader = BufReader::new(File::open(&path)?);
+            for line in reader.lines().skip(1) {
+                let line = line?;
+                let mut fields = line.split_whitespace();
+                let kind = match fields.next() {
+                    Some(kind) => kind,
+                    None => continue,
+                };
+                let vert: usize = parse(&path, fields.next())?;
+                match kind {
+                    // every worker knows the total of all ranks, whoever owns them
+                    "r" => {
+                        let rank: f64 = parse(&path, fields.next())?;
+                        state.total.0 += rank;
+                        state.total.1 += 1;
+                        if owned(vert) {
+                            state.ranks.insert(vert, rank);
+                        }
+                    }
+                    _ if !owned(vert) => {}
+                    "b" => {
+                        state.born.insert(vert, parse(&path, fields.next())?);
+                    }
+                    "e" => {
+                        let dsts = state.edges.entry(vert).or_insert(Vec::new());
+                        for field in fields {
+                            let (dst, added) = field.split_once('@').ok_or_else(|| {
+                                invalid_data(
+                                    &path,
+                                    &format!("expected <vertex>@<epoch>, got '{}'", field),
+                                )
+                            })?;
+                            dsts.push((parse(&path, Some(dst))?, parse(&path, Some(added))?));
+                        }
+                    }
+                    _ => return Err(invalid_data(&path, &format!("unexpected line '{}'", line))),
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    fn is_complete(&self, epoch: u64) -> io::Result<bool> {
+        let files = self.worker_files(epoch)?;
+        match files.first() {
+            Some(path) => Ok(header(path)?.2 == files.len()),
+            None => Ok(false),
+        }
+    }
+
+    fn worker_files(&self, epoch: u64) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(self.dir.join(epoch.to_string()))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// The epoch, worker index and number of workers a worker file starts with.
+fn header(path: &Path) -> io::Result<(u64, usize, usize)> {
+    let mut line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut line)?;
+    let fields = line
+        .split_whitespace()
+        .map(|field| field.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| invalid_data(path, &err.to_string()))?;
+    match fields[..] {
+        [epoch, index, peers] => Ok((epoch, index as usize, peers as usize)),
+        _ => Err(invalid_data(
+            path,
+            "expected epoch, worker index and number of workers",
+        )),
+    }
+}
+
+fn parse<T>(path: &Path, field: Option<&str>) -> io::Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let field = field.ok_or_else(|| invalid_data(path, "line ends too early"))?;
+    field
+        .parse()
+        .map_err(|err| invalid_data(path, &format!("invalid number '{}': {}", field, err)))
+}
+
+fn invalid_data(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), message),
+    )
+}
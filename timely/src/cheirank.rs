@@ -0,0 +1,181 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use serde_json::json;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::OutputHandle;
+use timely::dataflow::{operators::*, Scope, Stream};
+
+use crate::dataflow::{self, Format, Params, State};
+use crate::telemetry::IterationStats;
+
+/// Calculates the CheiRank of each epoch of `edges`, the PageRank of the transposed graph, where a
+/// vertex ranks high if it cites many vertices that cite many others, like a review article does.
+/// Takes the same `params` and returns the same streams as `dataflow::pagerank`.
+pub fn cheirank<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    cheirank_from(edges, params, Rc::new(RefCell::new(State::default())))
+}
+
+/// Same as `cheirank`, continuing from the `state` of this worker, which holds the transposed
+/// graph, see `dataflow::pagerank_from`.
+pub fn cheirank_from<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    state: Rc<RefCell<State>>,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    dataflow::pagerank_from(&edges.map(|(src, dst)| (dst, src)), params, state)
+}
+
+/// Where a vertex is in the PageRank and in the CheiRank order of an epoch, counting from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Positions {
+    pub vertex: usize,
+    pub pagerank: usize,
+    pub cheirank: usize,
+}
+
+/// Orders the vertices of an epoch by both their `ranks` and their `cheiranks`: by the larger of
+/// their two positions first, so that the top `k` are the vertices in the top `k` of both, and by
+/// the smaller one next. Ties of the same two positions the other way around go to PageRank.
+pub fn order_2d(ranks: &[(usize, f64)], cheiranks: &[(usize, f64)]) -> Vec<Positions> {
+    let pagerank = positions(ranks);
+    let cheirank = positions(cheiranks);
+
+    // a vertex missing from one of them comes after all the others there
+    let vertices = pagerank
+        .keys()
+        .chain(cheirank.keys())
+        .collect::<HashSet<_>>();
+    let mut order = vertices
+        .into_iter()
+        .map(|vertex| Positions {
+            vertex: *vertex,
+            pagerank: *pagerank.get(vertex).unwrap_or(&(pagerank.len() + 1)),
+            cheirank: *cheirank.get(vertex).unwrap_or(&(cheirank.len() + 1)),
+        })
+        .collect::<Vec<_>>();
+    order.sort_by_key(|p| {
+        (
+            p.pagerank.max(p.cheirank),
+            p.pagerank.min(p.cheirank),
+            p.pagerank,
+            p.vertex,
+        )
+    });
+    order
+}
+
+/// The position of every vertex by its score, from the highest down, with ties broken by vertex.
+fn positions(scores: &[(usize, f64)]) -> HashMap<usize, usize> {
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|&(u, a), &(v, b)| b.partial_cmp(&a).unwrap().then(u.cmp(&v)));
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(position, (vertex, _))| (vertex, position + 1))
+        .collect()
+}
+
+/// Gathers the `ranks` and `cheiranks` of each epoch on worker 0, and prints the top `k` vertices
+/// of their 2D ordering, see `order_2d`, in the given `format`.
+pub fn write_top_2d<G>(
+    ranks: &Stream<G, (usize, f64)>,
+    cheiranks: &Stream<G, (usize, f64)>,
+    k: usize,
+    format: Format,
+) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    ranks.binary_frontier(
+        cheiranks,
+        Exchange::new(|_| 0),
+        Exchange::new(|_| 0),
+        "Result2d",
+        move |_capability, _info| {
+            let mut rank_stash = HashMap::new();
+            let mut rank_vec = Vec::new();
+            let mut cheirank_vec = Vec::new();
+            let mut header_printed = false;
+
+            move |input1, input2, _output: &mut OutputHandle<u64, (), _>| {
+                // hold on to final ranks of both until it is time
+                input1.for_each(|time, data| {
+                    data.swap(&mut rank_vec);
+                    let (ranks, _) = rank_stash
+                        .entry(time.retain())
+                        .or_insert((Vec::new(), Vec::new()));
+                    ranks.append(&mut rank_vec);
+                });
+                input2.for_each(|time, data| {
+                    data.swap(&mut cheirank_vec);
+                    let (_, cheiranks) = rank_stash
+                        .entry(time.retain())
+                        .or_insert((Vec::new(), Vec::new()));
+                    cheiranks.append(&mut cheirank_vec);
+                });
+
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                for (time, (ranks, cheiranks)) in rank_stash.iter_mut() {
+                    if frontiers.iter().all(|f| !f.less_equal(time)) {
+                        let order = order_2d(ranks, cheiranks);
+                        let top = order.iter().take(k);
+                        match format {
+                            Format::Text => {
+                                println!("--- year {:?} top {} in 2D ---", time.time(), k);
+                                for p in top {
+                                    println!(
+                                        "{:?} has PageRank position {} and CheiRank position {}",
+                                        p.vertex, p.pagerank, p.cheirank
+                                    );
+                                }
+                            }
+                            Format::Csv => {
+                                if !header_printed {
+                                    println!("epoch,position,vertex,pagerank,cheirank");
+                                    header_printed = true;
+                                }
+                                for (position, p) in top.enumerate() {
+                                    println!(
+                                        "{},{},{},{},{}",
+                                        time.time(),
+                                        position + 1,
+                                        p.vertex,
+                                        p.pagerank,
+                                        p.cheirank
+                                    );
+                                }
+                            }
+                            Format::Json => {
+                                let top = top
+                                    .map(|p| {
+                                        json!({
+                                            "vertex": p.vertex,
+                                            "pagerank": p.pagerank,
+                                            "cheirank": p.cheirank,
+                                        })
+                                    })
+                                    .collect::<Vec<_>>();
+                                println!("{}", json!({ "epoch": time.time(), "top_2d": top }));
+                            }
+                        }
+                        ranks.clear();
+                        cheiranks.clear();
+                    }
+                }
+                rank_stash
+                    .retain(|_key, (ranks, cheiranks)| !ranks.is_empty() || !cheiranks.is_empty());
+            }
+        },
+    )
+}
@@ -0,0 +1,732 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::ToSocketAddrs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::checkpoint::Checkpoint;
+use crate::config::Config;
+use crate::convergence::{Extrapolation, Norm, StopRule};
+use crate::dataflow::{Centrality, Decay, Format, Params, Schedule, Teleport, WarmStart};
+use crate::generators::Model;
+use crate::trustrank;
+
+pub const USAGE: &str = "\
+usage: pagerank [iterations|tolerance] [--config <path>] [options] [timely options]
+
+modes:
+    iterations            run a fixed number of iterations per epoch
+    tolerance             iterate until the residual reaches the tolerance
+
+options:
+    --config <path>           read the run from a TOML file, or JSON if it ends with .json,
+                              the options below override what it says
+    --start <epoch>           first epoch to report (default 1992)
+    --end <epoch>             last epoch to read
+    --snapshot-from <epoch>   load the epochs from here up to --start at once, and only report
+                              ranks from --start on
+    --data-dir <path>         directory of <epoch>-edges.txt files (default
+                              ../dataset/incremental)
+    --source <source>         files or synthetic (default files)
+    --model <model>           erdos-renyi, barabasi-albert, rmat or citation, synthetic only
+    --scale <scale>           size relative to HepPh (default 1.0), synthetic only
+    --seed <seed>             seed of the generator, synthetic only, and of the random walks of
+                              montecarlo (default 0)
+    --iterations <n>          iterations per epoch, required in iterations mode
+    --tolerance <x>           tolerance of the residual, required in tolerance mode
+    --norm <norm>             l1, l2, linf, relative, or scaled-l1 for the criterion of NetworkX
+                              (default linf), tolerance mode only
+    --max-iterations <n>      stop an epoch after n iterations, tolerance mode only
+    --measure <measure>       pagerank, katz, eigenvector, citerank, cheirank, 2d for pagerank
+                              and cheirank ordered together, hits for authority and hub
+                              scores, trustrank, anti-trustrank, or montecarlo for pagerank
+                              estimated by random walks (default pagerank)
+    --warm-start <mode>       cold, previous, mean or teleport (default previous)
+    --damping <d>             probability of following a citation (default 0.85)
+    --alpha <a>               attenuation of every citation, katz only (default 0.1)
+    --beta <b>                rank every vertex gets on top, katz only (default 1.0)
+    --tau <epochs>            how fast the teleport value decays with age, citerank only
+                              (default 2.6)
+    --decay <decay>           weight of citations by their age: none, exponential:<tau>,
+                              power:<exponent> or window:<epochs> (default none)
+    --schedule <schedule>     jacobi, gauss-seidel to pass changes on to the vertices of the
+                              same worker within an iteration, or blocks to rank strongly
+                              connected blocks one after another (default jacobi)
+    --extrapolation <kind>    jump ahead every few iterations: none, aitken:<period> or
+                              quadratic:<period> (default none)
+    --trusted <path>          trusted seeds, one vertex per line, trustrank only
+    --distrusted <path>       distrusted seeds, one vertex per line, anti-trustrank only
+    --anomaly-ratio <r>       report the vertices whose pagerank is at least r times their
+                              trustrank, trustrank only (default 10)
+    --walks <r>               random walks started from every vertex, montecarlo only
+                              (default 16)
+    --top-k <k>               number of top vertices printed per epoch (default 5)
+    --format <format>         text, csv or json (default text)
+    --summary                 print how many iterations each epoch needed at the end
+    --serve <address>         answer rank queries on this address, such as 127.0.0.1:7878,
+                              until stopped
+    --checkpoint-dir <path>   save the state of every worker there after each epoch
+    --restore                 continue after the last epoch saved in --checkpoint-dir
+    --workers <n>             worker threads per process (timely -w)
+    --processes <n>           number of processes (timely -n)
+    --process <i>             index of this process (timely -p)
+    --hostfile <path>         addresses of all processes (timely -h)
+    --local-processes <n>     start n processes on this machine, connected over localhost
+    --help                    print this message
+
+Any other argument is passed on to timely as it is.";
+
+/// Why the arguments could not be turned into `Args`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// `--help` was asked for
+    Help,
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Help => write!(f, "{}", USAGE),
+            Error::Invalid(message) => write!(f, "error: {}\n\n{}", message, USAGE),
+        }
+    }
+}
+
+/// What is computed for every vertex.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measure {
+    /// `dataflow::pagerank`
+    PageRank,
+    /// `dataflow::pagerank` with `Centrality::Katz`
+    Katz,
+    /// `dataflow::pagerank` with `Centrality::Eigenvector`
+    Eigenvector,
+    /// `dataflow::pagerank` with `Centrality::CiteRank`
+    CiteRank,
+    /// `cheirank::cheirank`, PageRank on the transposed graph
+    CheiRank,
+    /// both `dataflow::pagerank` and `cheirank::cheirank`, whose top vertices are reported by
+    /// `cheirank::order_2d`
+    TwoDimensional,
+    /// `hits::hits`, whose authorities are reported like ranks, followed by the hubs
+    Hits,
+    /// `trustrank::trustrank`, whose ranks are reported, followed by the vertices whose PageRank
+    /// is out of proportion to it, see `trustrank::anomalies`
+    TrustRank,
+    /// `trustrank::anti_trustrank`
+    AntiTrustRank,
+    /// `montecarlo::montecarlo`, whose estimates are reported along with their bounds
+    MonteCarlo,
+}
+
+impl Measure {
+    /// How the scores are named in the output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Measure::PageRank => "rank",
+            Measure::Katz => "katz",
+            Measure::Eigenvector => "eigenvector",
+            Measure::CiteRank => "citerank",
+            Measure::CheiRank => "cheirank",
+            Measure::TwoDimensional => "rank",
+            Measure::Hits => "authority",
+            Measure::TrustRank => "trust",
+            Measure::AntiTrustRank => "distrust",
+            Measure::MonteCarlo => "rank",
+        }
+    }
+}
+
+impl FromStr for Measure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pagerank" => Ok(Measure::PageRank),
+            "katz" => Ok(Measure::Katz),
+            "eigenvector" => Ok(Measure::Eigenvector),
+            "citerank" => Ok(Measure::CiteRank),
+            "cheirank" => Ok(Measure::CheiRank),
+            "2d" => Ok(Measure::TwoDimensional),
+            "hits" => Ok(Measure::Hits),
+            "trustrank" => Ok(Measure::TrustRank),
+            "anti-trustrank" => Ok(Measure::AntiTrustRank),
+            "montecarlo" => Ok(Measure::MonteCarlo),
+            _ => Err(format!(
+                "unknown measure '{}', expected one of: pagerank, katz, eigenvector, citerank, \
+                 cheirank, 2d, hits, trustrank, anti-trustrank, montecarlo",
+                s
+            )),
+        }
+    }
+}
+
+/// Where the edges of every epoch come from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    /// `<epoch>-edges.txt` files in a directory, where every worker reads its share of the lines
+    Files(PathBuf),
+    /// `generators::generator`, where every worker generates its own share
+    Synthetic { model: Model, scale: f64, seed: u64 },
+}
+
+/// Everything the `pagerank` binary needs to know about a run. Epochs are read from `first` on, and
+/// ranks are reported from `start` on, see `utils::start_at`. A restored run starts after the epoch
+/// it was `restored` from.
+#[derive(Clone, Debug)]
+pub struct Args {
+    /// the run as given, to be echoed with the results
+    pub config: Config,
+    pub measure: Measure,
+    pub params: Params,
+    /// the vertices the random surfer jumps to, the seeds of trustrank and anti-trustrank
+    pub teleport: Teleport,
+    /// the PageRank to TrustRank ratio from which vertices are reported, see `trustrank::anomalies`
+    pub anomaly_ratio: f64,
+    /// the random walks started from every vertex, see `montecarlo::montecarlo`
+    pub walks: usize,
+    /// the seed of the random walks
+    pub seed: u64,
+    pub source: Source,
+    /// the epoch the first batch of a synthetic source belongs to
+    pub origin: u64,
+    pub first: u64,
+    pub start: u64,
+    pub end: u64,
+    pub checkpoint: Option<Checkpoint>,
+    pub restored: Option<u64>,
+    pub top_k: usize,
+    pub format: Format,
+    pub summary: bool,
+    /// the address rank queries are answered on
+    pub serve: Option<String>,
+    /// the number of processes to start on this machine, if this is the process starting them
+    pub launch: Option<usize>,
+    /// whether this is one of the processes started with `--local-processes`
+    pub launched: bool,
+    /// the program name followed by every argument meant for timely
+    pub timely_args: Vec<String>,
+}
+
+impl Args {
+    /// Parses the arguments of the program, the program name included.
+    pub fn parse<I>(args: I) -> Result<Args, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let program = args.next().unwrap_or_else(|| "pagerank".to_string());
+
+        let mut config_path = None;
+        let mut mode = None;
+        let mut flags = Vec::new();
+        let mut passed_on = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = split_flag(&arg);
+            let mut value = || value_of(&flag, inline_value, &mut args);
+
+            // timely's own worker flags, which may also hold their value as `-w4`, end up in the
+            // config like their long forms, so that the echoed config repeats the run
+            if let Some(long) = arg.get(..2).and_then(topology_flag) {
+                let value = match &arg[2..] {
+                    "" => value()?,
+                    attached => attached.to_string(),
+                };
+                flags.push((long.to_string(), value));
+                continue;
+            }
+
+            match flag.as_str() {
+                "--help" => return Err(Error::Help),
+                "iterations" | "tolerance" if mode.is_none() => mode = Some(flag.clone()),
+                "--config" => config_path = Some(value()?),
+                "--summary" | "--restore" => flags.push((flag.clone(), "true".to_string())),
+                "--start" | "--end" | "--snapshot-from" | "--data-dir" | "--source" | "--model"
+                | "--scale" | "--seed" | "--iterations" | "--tolerance" | "--norm"
+                | "--max-iterations" | "--measure" | "--warm-start" | "--damping" | "--alpha"
+                | "--beta" | "--tau" | "--decay" | "--schedule" | "--extrapolation"
+                | "--trusted" | "--distrusted" | "--anomaly-ratio" | "--walks" | "--top-k"
+                | "--format" | "--serve" | "--workers" | "--processes" | "--process"
+                | "--hostfile" | "--local-processes" | "--checkpoint-dir" => {
+                    flags.push((flag.clone(), value()?))
+                }
+                _ => passed_on.push(arg),
+            }
+        }
+
+        // flags override the config file wherever they appear
+        let mut config = match config_path {
+            Some(path) => Config::load(path).map_err(Error::Invalid)?,
+            None => Config::default(),
+        };
+        if mode.is_some() {
+            config.stop.mode = mode;
+        }
+        for (flag, value) in flags {
+            set(&mut config, &flag, &value)?;
+        }
+
+        let mut timely_args = vec![program];
+        timely_args.extend(config.topology.timely_args());
+        timely_args.extend(passed_on);
+        Args::from_config(config, timely_args)
+    }
+
+    /// Checks that `config` describes a run, and turns it into one.
+    pub fn from_config(config: Config, timely_args: Vec<String>) -> Result<Args, Error> {
+        let stop = &config.stop;
+        let stop_rule = match stop.mode.as_deref() {
+            Some("iterations") => {
+                if stop.tolerance.is_some() || stop.norm.is_some() || stop.max_iterations.is_some()
+                {
+                    return Err(invalid(
+                        "tolerance, norm and max-iterations only apply to tolerance mode",
+                    ));
+                }
+                StopRule::iterations(
+                    stop.iterations
+                        .ok_or_else(|| invalid("iterations mode needs --iterations"))?,
+                )
+            }
+            Some("tolerance") => {
+                if stop.iterations.is_some() {
+                    return Err(invalid(
+                        "iterations only applies to iterations mode, use max-iterations",
+                    ));
+                }
+                let tolerance = stop
+                    .tolerance
+                    .ok_or_else(|| invalid("tolerance mode needs --tolerance"))?;
+                if tolerance.is_nan() || tolerance <= 0.0 {
+                    return Err(invalid("tolerance must be positive"));
+                }
+                let norm = match &stop.norm {
+                    Some(norm) => parse("norm", norm)?,
+                    None => Norm::LInf,
+                };
+                let stop_rule = StopRule::tolerance(norm, tolerance);
+                match stop.max_iterations {
+                    Some(max_iterations) => stop_rule.with_max_iterations(max_iterations),
+                    None => stop_rule,
+                }
+            }
+            Some(mode) => {
+                return Err(invalid(&format!(
+                    "unknown mode '{}', expected iterations or tolerance",
+                    mode
+                )))
+            }
+            None => return Err(invalid("expected a mode, either iterations or tolerance")),
+        };
+
+        let algorithm = &config.algorithm;
+        let damping = algorithm.damping;
+        if !(damping > 0.0 && damping < 1.0) {
+            return Err(invalid("damping must be between 0 and 1"));
+        }
+        let measure: Measure = parse("measure", &algorithm.measure)?;
+        let centrality = match measure {
+            Measure::Katz => {
+                let (alpha, beta) = (algorithm.alpha, algorithm.beta);
+                if !(alpha > 0.0 && alpha.is_finite() && beta > 0.0 && beta.is_finite()) {
+                    return Err(invalid("alpha and beta must be positive"));
+                }
+                Centrality::Katz { alpha, beta }
+            }
+            Measure::Eigenvector => Centrality::Eigenvector,
+            Measure::CiteRank => {
+                let tau = algorithm.tau;
+                if !(tau > 0.0 && tau.is_finite()) {
+                    return Err(invalid("tau must be positive"));
+                }
+                Centrality::CiteRank { tau }
+            }
+            Measure::PageRank
+            | Measure::CheiRank
+            | Measure::TwoDimensional
+            | Measure::Hits
+            | Measure::TrustRank
+            | Measure::AntiTrustRank
+            | Measure::MonteCarlo => Centrality::PageRank,
+        };
+        let warm_start: WarmStart = parse("warm-start", &algorithm.warm_start)?;
+        let decay: Decay = parse("decay", &algorithm.decay)?;
+        let schedule: Schedule = parse("schedule", &algorithm.schedule)?;
+        let extrapolation: Extrapolation = parse("extrapolation", &algorithm.extrapolation)?;
+        if schedule != Schedule::Jacobi && centrality == Centrality::Eigenvector {
+            return Err(invalid(&format!(
+                "{} does not know the sum of all ranks, which eigenvector divides by",
+                algorithm.schedule
+            )));
+        }
+        if schedule == Schedule::Blocks && extrapolation != Extrapolation::None {
+            return Err(invalid("blocks does not extrapolate"));
+        }
+
+        // fail here rather than in every worker, and rather than ranking nothing
+        let seeds = match measure {
+            Measure::TrustRank => Some(("trusted", &algorithm.trusted)),
+            Measure::AntiTrustRank => Some(("distrusted", &algorithm.distrusted)),
+            _ => None,
+        };
+        let teleport = match seeds {
+            Some((name, Some(path))) => {
+                let seeds = trustrank::read_seeds(path)
+                    .map_err(|err| invalid(&format!("cannot read {}: {}", path.display(), err)))?;
+                if seeds.is_empty() {
+                    return Err(invalid(&format!("no {} seeds in {}", name, path.display())));
+                }
+                Teleport::seeds(seeds)
+            }
+            Some((name, None)) => {
+                return Err(invalid(&format!("{} needs --{}", algorithm.measure, name)))
+            }
+            None => Teleport::Uniform,
+        };
+        let anomaly_ratio = algorithm.anomaly_ratio;
+        if !(anomaly_ratio > 0.0 && anomaly_ratio.is_finite()) {
+            return Err(invalid("anomaly-ratio must be positive"));
+        }
+        if algorithm.walks == 0 {
+            return Err(invalid("walks must be at least 1"));
+        }
+
+        let input = &config.input;
+        let (start, end) = match input.end {
+            Some(end) if end >= input.start => (input.start, end),
+            Some(end) => {
+                return Err(invalid(&format!(
+                    "end {} is before start {}",
+                    end, input.start
+                )))
+            }
+            None => return Err(invalid("--end is required")),
+        };
+        let first = match input.snapshot_from {
+            Some(first) if first <= start => first,
+            Some(first) => {
+                return Err(invalid(&format!(
+                    "snapshot-from {} is after start {}",
+                    first, start
+                )))
+            }
+            None => start,
+        };
+        let origin = first;
+
+        // a restored run continues after the last epoch saved
+        if config.checkpoint.dir.is_some()
+            && matches!(
+                measure,
+                Measure::Hits | Measure::TwoDimensional | Measure::TrustRank | Measure::MonteCarlo
+            )
+        {
+            return Err(invalid(&format!(
+                "checkpoints do not hold the state of {}",
+                algorithm.measure
+            )));
+        }
+        if config.checkpoint.dir.is_some() && schedule == Schedule::Blocks {
+            return Err(invalid("checkpoints do not hold the state of blocks"));
+        }
+        let checkpoint = config
+            .checkpoint
+            .dir
+            .as_ref()
+            .map(|dir| Checkpoint::new(dir).with_config(config.clone()));
+        let restored = match (&checkpoint, config.checkpoint.restore) {
+            (Some(checkpoint), true) => {
+                let unreadable =
+                    |err: std::io::Error| invalid(&format!("cannot read the checkpoint: {}", err));
+                let latest = checkpoint
+                    .latest()
+                    .map_err(unreadable)?
+                    .ok_or_else(|| invalid("no complete checkpoint to restore from"))?;
+                // the state of another measure or input would be mixed with the one of this run
+                let saved = checkpoint
+                    .config(latest)
+                    .map_err(unreadable)?
+                    .ok_or_else(|| invalid("the checkpoint does not say which run saved it"))?;
+                if let Some(section) = saved.differs_in_state(&config) {
+                    return Err(invalid(&format!(
+                        "the checkpoint was saved by a run with another {} section",
+                        section
+                    )));
+                }
+                Some(latest)
+            }
+            (None, true) => return Err(invalid("restore needs --checkpoint-dir")),
+            (_, false) => None,
+        };
+        let (first, start) = match restored {
+            Some(_) if input.snapshot_from.is_some() => {
+                return Err(invalid("a restored run cannot load a snapshot"))
+            }
+            Some(epoch) if epoch < end => (epoch + 1, epoch + 1),
+            Some(epoch) => {
+                return Err(invalid(&format!(
+                    "the checkpoint already covers epoch {}",
+                    epoch
+                )))
+            }
+            None => (first, start),
+        };
+
+        let source = match input.source.as_str() {
+            "files" => {
+                // fail here rather than in every worker once it gets to a missing epoch
+                for epoch in first..=end {
+                    let path = input.data_dir.join(format!("{}-edges.txt", epoch));
+                    if !path.is_file() {
+                        return Err(invalid(&format!("{} does not exist", path.display())));
+                    }
+                }
+                Source::Files(input.data_dir.clone())
+            }
+            "synthetic" => {
+                if input.scale.is_nan() || input.scale <= 0.0 {
+                    return Err(invalid("scale must be positive"));
+                }
+                Source::Synthetic {
+                    model: parse("model", &input.model)?,
+                    scale: input.scale,
+                    seed: input.seed,
+                }
+            }
+            source => {
+                return Err(invalid(&format!(
+                    "unknown source '{}', expected files or synthetic",
+                    source
+                )))
+            }
+        };
+
+        let output = &config.output;
+        if output.top_k == 0 {
+            return Err(invalid("top-k must be at least 1"));
+        }
+        let format: Format = parse("format", &output.format)?;
+        if let Some(addr) = &output.serve {
+            addr.to_socket_addrs()
+                .map_err(|err| invalid(&format!("invalid address '{}': {}", addr, err)))?;
+        }
+
+        // the starting process passes `--processes`, `--process` and `--hostfile` to the others
+        let topology = &config.topology;
+        let (launch, launched) = match topology.local_processes {
+            Some(0) => return Err(invalid("local-processes must be at least 1")),
+            Some(processes) if matches!(topology.processes, Some(n) if n != processes) => {
+                return Err(invalid("processes differs from local-processes"))
+            }
+            Some(_) if topology.process.is_some() => (None, true),
+            Some(_) if topology.hostfile.is_some() => {
+                return Err(invalid("local-processes writes its own hostfile"))
+            }
+            Some(processes) => (Some(processes), false),
+            None => (None, false),
+        };
+
+        Ok(Args {
+            measure,
+            params: Params::new(stop_rule)
+                .with_warm_start(warm_start)
+                .with_damping(damping)
+                .with_centrality(centrality)
+                .with_decay(decay)
+                .with_schedule(schedule)
+                .with_extrapolation(extrapolation),
+            teleport,
+            anomaly_ratio,
+            walks: algorithm.walks,
+            seed: input.seed,
+            source,
+            origin,
+            first,
+            start,
+            end,
+            checkpoint,
+            restored,
+            top_k: output.top_k,
+            format,
+            summary: output.summary,
+            serve: output.serve.clone(),
+            launch,
+            launched,
+            timely_args,
+            config,
+        })
+    }
+}
+
+/// Named flags of the examples, which take their values like those of `Args`, except for the
+/// `switches`, which take none. Every other argument is meant for timely.
+#[derive(Clone, Debug, Default)]
+pub struct Flags {
+    values: HashMap<String, String>,
+    switches: HashSet<String>,
+    /// the program name followed by every argument meant for timely
+    pub timely_args: Vec<String>,
+}
+
+impl Flags {
+    /// Parses the arguments of the program, the program name included, knowing `flags` and
+    /// `switches`.
+    pub fn parse<I>(args: I, flags: &[&str], switches: &[&str]) -> Result<Flags, Error>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let program = args.next().unwrap_or_default();
+        let mut parsed = Flags {
+            timely_args: vec![program],
+            ..Flags::default()
+        };
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = split_flag(&arg);
+            if flag == "--help" {
+                return Err(Error::Help);
+            } else if switches.contains(&flag.as_str()) {
+                parsed.switches.insert(flag);
+            } else if flags.contains(&flag.as_str()) {
+                let value = value_of(&flag, inline_value, &mut args)?;
+                parsed.values.insert(flag, value);
+            } else {
+                parsed.timely_args.push(arg);
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The value of `flag`, if it was given.
+    pub fn get<T>(&self, flag: &str) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.values
+            .get(flag)
+            .map(|value| parse(flag, value))
+            .transpose()
+    }
+
+    /// The value of `flag`, which has to be given.
+    pub fn required<T>(&self, flag: &str) -> Result<T, Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.get(flag)?
+            .ok_or_else(|| invalid(&format!("{} is required", flag)))
+    }
+
+    /// The comma-separated values of `flag`, if it was given.
+    pub fn list<T>(&self, flag: &str) -> Result<Option<Vec<T>>, Error>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.values
+            .get(flag)
+            .map(|list| list.split(',').map(|item| parse(flag, item)).collect())
+            .transpose()
+    }
+
+    /// Whether `switch` was given.
+    pub fn switch(&self, switch: &str) -> bool {
+        self.switches.contains(switch)
+    }
+}
+
+/// Splits a flag from its value given as `--flag=value`. Flags take their value either that way
+/// or as the next argument, see `value_of`.
+fn split_flag(arg: &str) -> (String, Option<&str>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if arg.starts_with("--") => (flag.to_string(), Some(value)),
+        _ => (arg.to_string(), None),
+    }
+}
+
+/// The value of `flag`, either given along with it or as the next of `args`.
+fn value_of<I>(flag: &str, inline_value: Option<&str>, args: &mut I) -> Result<String, Error>
+where
+    I: Iterator<Item = String>,
+{
+    match inline_value {
+        Some(value) => Ok(value.to_string()),
+        None => args
+            .next()
+            .ok_or_else(|| Error::Invalid(format!("{} needs a value", flag))),
+    }
+}
+
+/// The flag of the config standing for one of timely's short flags.
+fn topology_flag(flag: &str) -> Option<&'static str> {
+    match flag {
+        "-w" => Some("--workers"),
+        "-n" => Some("--processes"),
+        "-p" => Some("--process"),
+        "-h" => Some("--hostfile"),
+        _ => None,
+    }
+}
+
+/// Puts the value of a command-line flag into its place in `config`.
+fn set(config: &mut Config, flag: &str, value: &str) -> Result<(), Error> {
+    match flag {
+        "--start" => config.input.start = parse(flag, value)?,
+        "--end" => config.input.end = Some(parse(flag, value)?),
+        "--snapshot-from" => config.input.snapshot_from = Some(parse(flag, value)?),
+        "--data-dir" => config.input.data_dir = PathBuf::from(value),
+        "--source" => config.input.source = value.to_string(),
+        "--model" => config.input.model = value.to_string(),
+        "--scale" => config.input.scale = parse(flag, value)?,
+        "--seed" => config.input.seed = parse(flag, value)?,
+        "--iterations" => config.stop.iterations = Some(parse(flag, value)?),
+        "--tolerance" => config.stop.tolerance = Some(parse(flag, value)?),
+        "--norm" => config.stop.norm = Some(value.to_string()),
+        "--max-iterations" => config.stop.max_iterations = Some(parse(flag, value)?),
+        "--measure" => config.algorithm.measure = value.to_string(),
+        "--warm-start" => config.algorithm.warm_start = value.to_string(),
+        "--damping" => config.algorithm.damping = parse(flag, value)?,
+        "--alpha" => config.algorithm.alpha = parse(flag, value)?,
+        "--beta" => config.algorithm.beta = parse(flag, value)?,
+        "--tau" => config.algorithm.tau = parse(flag, value)?,
+        "--decay" => config.algorithm.decay = value.to_string(),
+        "--schedule" => config.algorithm.schedule = value.to_string(),
+        "--extrapolation" => config.algorithm.extrapolation = value.to_string(),
+        "--trusted" => config.algorithm.trusted = Some(PathBuf::from(value)),
+        "--distrusted" => config.algorithm.distrusted = Some(PathBuf::from(value)),
+        "--anomaly-ratio" => config.algorithm.anomaly_ratio = parse(flag, value)?,
+        "--walks" => config.algorithm.walks = parse(flag, value)?,
+        "--top-k" => config.output.top_k = parse(flag, value)?,
+        "--format" => config.output.format = value.to_string(),
+        "--summary" => config.output.summary = true,
+        "--serve" => config.output.serve = Some(value.to_string()),
+        "--checkpoint-dir" => config.checkpoint.dir = Some(PathBuf::from(value)),
+        "--restore" => config.checkpoint.restore = true,
+        "--workers" => config.topology.workers = Some(parse(flag, value)?),
+        "--processes" => config.topology.processes = Some(parse(flag, value)?),
+        "--process" => config.topology.process = Some(parse(flag, value)?),
+        "--hostfile" => config.topology.hostfile = Some(PathBuf::from(value)),
+        "--local-processes" => config.topology.local_processes = Some(parse(flag, value)?),
+        _ => unreachable!("{} is not a flag of the config", flag),
+    }
+    Ok(())
+}
+
+fn parse<T>(name: &str, value: &str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| invalid(&format!("invalid value '{}' for {}: {}", value, name, err)))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::Invalid(message.to_string())
+}
@@ -0,0 +1,119 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Writes a timely hostfile for `processes` processes on this machine, one `127.0.0.1:<port>` line
+/// per process, with ports the system currently has free.
+pub fn write_local_hostfile(path: &Path, processes: usize) -> io::Result<()> {
+    // keep all listeners open until every port is known, so that no port is handed out twice
+    let listeners = (0..processes)
+        .map(|_| TcpListener::bind("127.0.0.1:0"))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut hostfile = String::new();
+    for listener in &listeners {
+        hostfile.push_str(&format!("{}\n", listener.local_addr()?));
+    }
+    drop(listeners);
+
+    fs::write(path, hostfile)
+}
+
+/// Runs `program` with `args` as `processes` processes on this machine, connected through a fresh
+/// hostfile at `hostfile`, each told which of them it is with `--processes`, `--process` and
+/// `--hostfile`. Waits for all of them, and stops the others as soon as one fails, since they
+/// would wait for it forever. What they print is passed on line by line, see `forward`. Returns
+/// whether all of them succeeded.
+pub fn launch(
+    program: &Path,
+    args: &[String],
+    processes: usize,
+    hostfile: &Path,
+) -> io::Result<bool> {
+    write_local_hostfile(hostfile, processes)?;
+
+    let mut children = Vec::new();
+    let mut forwarders = Vec::new();
+    for process in 0..processes {
+        let child = Command::new(program)
+            .args(args)
+            .arg("--processes")
+            .arg(processes.to_string())
+            .arg("--process")
+            .arg(process.to_string())
+            .arg("--hostfile")
+            .arg(hostfile)
+            .stdout(Stdio::piped())
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                let stdout = child.stdout.take().expect("stdout is piped");
+                forwarders.push(thread::spawn(move || forward(stdout)));
+                children.push(child);
+            }
+            Err(err) => {
+                kill_all(&mut children);
+                return Err(err);
+            }
+        }
+    }
+
+    let mut running = children.len();
+    let mut success = true;
+    while running > 0 {
+        running = 0;
+        for child in &mut children {
+            match child.try_wait()? {
+                Some(status) if !status.success() => success = false,
+                Some(_) => {}
+                None => running += 1,
+            }
+        }
+        if !success {
+            kill_all(&mut children);
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    // the pipes close with the processes, so that every forwarder gets to the end
+    for forwarder in forwarders {
+        forwarder.join().expect("forwarding panicked")?;
+    }
+
+    fs::remove_file(hostfile)?;
+    Ok(success)
+}
+
+/// Passes on what a process prints to stdout, except for the messages of timely about connecting
+/// to the other processes, which go to stderr so that they do not end up among the results.
+fn forward(stdout: ChildStdout) -> io::Result<()> {
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if is_timely_message(&line) {
+            writeln!(io::stderr().lock(), "{}", line)?;
+        } else {
+            writeln!(io::stdout().lock(), "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `line` is printed by timely rather than by the program, such as
+/// `worker 1:\terror connecting to worker 0: ...; retrying`.
+fn is_timely_message(line: &str) -> bool {
+    line.strip_prefix("worker ")
+        .and_then(|rest| rest.split_once(":\t"))
+        .is_some_and(|(index, _)| index.parse::<usize>().is_ok())
+}
+
+fn kill_all(children: &mut [Child]) {
+    for child in children {
+        // the child may have exited already, which is all we want
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
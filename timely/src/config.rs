@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::dataflow::Format;
+use crate::utils;
+
+/// Complete description of a run, as read from a TOML or JSON file and completed by the flags of
+/// the `pagerank` binary. Every field has a default, so a file only needs what differs. Values with
+/// a fixed set of choices are kept as written and checked by `cli::Args::from_config`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub input: Input,
+    pub algorithm: Algorithm,
+    pub stop: Stop,
+    pub output: Output,
+    pub topology: Topology,
+    pub checkpoint: Checkpointing,
+}
+
+/// Where the edges of every epoch come from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Input {
+    /// `files` reads `<epoch>-edges.txt` from `data_dir`, `synthetic` generates the edges
+    pub source: String,
+    pub data_dir: PathBuf,
+    pub start: u64,
+    pub end: Option<u64>,
+    /// the edges of the epochs from here up to `start` are loaded at once as the graph `start`
+    /// begins with, without reporting ranks for them
+    pub snapshot_from: Option<u64>,
+    /// model, scale and seed of `generators::generator`, for the synthetic source, the seed also
+    /// seeds the random walks of `montecarlo`
+    pub model: String,
+    pub scale: f64,
+    pub seed: u64,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            source: "files".to_string(),
+            data_dir: PathBuf::from(utils::DATA_DIR_PATH),
+            start: utils::FIRST_YEAR,
+            end: None,
+            snapshot_from: None,
+            model: "citation".to_string(),
+            scale: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Algorithm {
+    /// `pagerank`, `katz`, `eigenvector`, `citerank`, `cheirank`, `2d`, `hits`, `trustrank`,
+    /// `anti-trustrank` or `montecarlo`, see `cli::Measure`
+    pub measure: String,
+    pub damping: f64,
+    /// attenuation and base rank of `katz`
+    pub alpha: f64,
+    pub beta: f64,
+    /// decay time of the teleport value of `citerank`, in epochs
+    pub tau: f64,
+    /// weight of citations by their age, see `dataflow::Decay`
+    pub decay: String,
+    /// `jacobi`, `gauss-seidel` or `blocks`, see `dataflow::Schedule`
+    pub schedule: String,
+    /// `none`, `aitken:<period>` or `quadratic:<period>`, see `convergence::Extrapolation`
+    pub extrapolation: String,
+    pub warm_start: String,
+    /// seeds of `trustrank` and `anti-trustrank`, see `trustrank::read_seeds`
+    pub trusted: Option<PathBuf>,
+    pub distrusted: Option<PathBuf>,
+    /// how many times its TrustRank the PageRank of a vertex has to be to be reported by
+    /// `trustrank`
+    pub anomaly_ratio: f64,
+    /// random walks started from every vertex by `montecarlo`
+    pub walks: usize,
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm {
+            measure: "pagerank".to_string(),
+            damping: 0.85,
+            alpha: 0.1,
+            beta: 1.0,
+            tau: 2.6,
+            decay: "none".to_string(),
+            schedule: "jacobi".to_string(),
+            extrapolation: "none".to_string(),
+            warm_start: "previous".to_string(),
+            trusted: None,
+            distrusted: None,
+            anomaly_ratio: 10.0,
+            walks: 16,
+        }
+    }
+}
+
+/// The stop rule: `iterations` runs a fixed number of them, `tolerance` iterates until the
+/// residual under `norm` reaches `tolerance`, or until `max_iterations`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stop {
+    pub mode: Option<String>,
+    pub iterations: Option<u64>,
+    pub tolerance: Option<f64>,
+    pub norm: Option<String>,
+    pub max_iterations: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Output {
+    pub top_k: usize,
+    pub format: String,
+    pub summary: bool,
+    /// address to answer rank queries on, see `server::RankStore::answer`
+    pub serve: Option<String>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            top_k: 5,
+            format: "text".to_string(),
+            summary: false,
+            serve: None,
+        }
+    }
+}
+
+/// How many workers and processes timely runs, left to timely's own defaults when not given.
+/// `local_processes` starts that many processes on this machine instead of only this one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Topology {
+    pub workers: Option<usize>,
+    pub processes: Option<usize>,
+    pub process: Option<usize>,
+    pub hostfile: Option<PathBuf>,
+    pub local_processes: Option<usize>,
+}
+
+/// Where the state of every worker is saved after each epoch, and whether a run continues from the
+/// last epoch saved there.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Checkpointing {
+    pub dir: Option<PathBuf>,
+    pub restore: bool,
+}
+
+impl Topology {
+    /// The same settings as timely's command-line arguments.
+    pub fn timely_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(workers) = self.workers {
+            args.extend(["-w".to_string(), workers.to_string()]);
+        }
+        if let Some(processes) = self.processes {
+            args.extend(["-n".to_string(), processes.to_string()]);
+        }
+        if let Some(process) = self.process {
+            args.extend(["-p".to_string(), process.to_string()]);
+        }
+        if let Some(hostfile) = &self.hostfile {
+            args.extend(["-h".to_string(), hostfile.display().to_string()]);
+        }
+        args
+    }
+}
+
+impl Config {
+    /// Reads a config from `path`, as JSON if it ends with `.json` and as TOML otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        let config = if matches!(path.extension().and_then(|ext| ext.to_str()), Some("json")) {
+            serde_json::from_str(&text).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(&text).map_err(|err| err.to_string())
+        };
+        config.map_err(|err| format!("invalid config {}: {}", path.display(), err))
+    }
+
+    /// The first section of the config deciding the state of every worker in which `other`
+    /// differs, if any: the input apart from which epochs are read, the algorithm and the stop
+    /// rule. A run only continues from a checkpoint saved by a run that agrees on all of them.
+    pub fn differs_in_state(&self, other: &Config) -> Option<&'static str> {
+        let epochs_aside = |input: &Input| Input {
+            start: 0,
+            end: None,
+            snapshot_from: None,
+            ..input.clone()
+        };
+        if epochs_aside(&self.input) != epochs_aside(&other.input) {
+            Some("input")
+        } else if self.algorithm != other.algorithm {
+            Some("algorithm")
+        } else if self.stop != other.stop {
+            Some("stop")
+        } else {
+            None
+        }
+    }
+
+    /// The config as TOML, which `load` reads back into the same config.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+
+    /// Prints the config ahead of the results in `format`, so that they can be reproduced later:
+    /// as TOML, commented out in CSV, and as a single object in JSON.
+    pub fn print(&self, format: Format) {
+        match format {
+            Format::Text => {
+                println!("--- config ---");
+                print!("{}", self.to_toml());
+            }
+            Format::Csv => {
+                for line in self.to_toml().lines() {
+                    println!("# {}", line);
+                }
+            }
+            Format::Json => {
+                println!("{{\"config\":{}}}", serde_json::to_string(self).unwrap());
+            }
+        }
+    }
+}
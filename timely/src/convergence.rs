@@ -0,0 +1,270 @@
+use std::str::FromStr;
+
+use abomonation_derive::Abomonation;
+
+/// Vector norm used to measure how much the ranks changed between two iterations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Norm {
+    /// sum of absolute differences
+    L1,
+    /// euclidean length of the differences
+    L2,
+    /// maximum absolute difference, the criterion used by the Spark solution
+    LInf,
+    /// L1 norm of the differences divided by the L1 norm of the new ranks
+    Relative,
+    /// `Relative` divided by the number of vertices, the criterion used by NetworkX: it stops once
+    /// the L1 norm of the differences of its ranks, which sum up to 1, is below `N * tol`, so the
+    /// tolerance is its `tol`. This is the L1 norm divided by `N^2` while the ranks sum up to `N`
+    ScaledL1,
+}
+
+impl FromStr for Norm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "l1" => Ok(Norm::L1),
+            "l2" => Ok(Norm::L2),
+            "linf" | "max" => Ok(Norm::LInf),
+            "relative" | "rel" => Ok(Norm::Relative),
+            "scaled-l1" | "networkx" => Ok(Norm::ScaledL1),
+            _ => Err(format!(
+                "unknown norm '{}', expected one of: l1, l2, linf, relative, scaled-l1",
+                s
+            )),
+        }
+    }
+}
+
+/// Partial sums from which every `Norm` can be derived. Residuals computed by different workers
+/// are merged before any norm is taken, so the result does not depend on the number of workers.
+#[derive(Abomonation, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Residual {
+    pub abs_sum: f64,
+    pub squared_sum: f64,
+    pub max: f64,
+    pub rank_sum: f64,
+    /// the number of vertices accounted for
+    pub count: u64,
+}
+
+impl Residual {
+    /// Accounts for one vertex whose rank changed from `old_rank` to `new_rank`.
+    pub fn add(&mut self, new_rank: f64, old_rank: f64) {
+        let diff = (new_rank - old_rank).abs();
+        self.abs_sum += diff;
+        self.squared_sum += diff * diff;
+        self.max = self.max.max(diff);
+        self.rank_sum += new_rank.abs();
+        self.count += 1;
+    }
+
+    pub fn merge(&mut self, other: &Residual) {
+        self.abs_sum += other.abs_sum;
+        self.squared_sum += other.squared_sum;
+        self.max = self.max.max(other.max);
+        self.rank_sum += other.rank_sum;
+        self.count += other.count;
+    }
+
+    pub fn value(&self, norm: Norm) -> f64 {
+        match norm {
+            Norm::L1 => self.abs_sum,
+            Norm::L2 => self.squared_sum.sqrt(),
+            Norm::LInf => self.max,
+            Norm::Relative => {
+                if self.rank_sum > 0.0 {
+                    self.abs_sum / self.rank_sum
+                } else {
+                    0.0
+                }
+            }
+            Norm::ScaledL1 => {
+                if self.count > 0 {
+                    self.value(Norm::Relative) / (self.count as f64)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Decides when the iteration of an epoch stops: once the residual under `norm` is no longer
+/// above `tolerance`, or after `max_iterations` iterations, whichever comes first.
+#[derive(Clone, Copy, Debug)]
+pub struct StopRule {
+    pub norm: Norm,
+    pub tolerance: Option<f64>,
+    pub max_iterations: Option<u64>,
+}
+
+impl StopRule {
+    /// Runs a fixed number of iterations per epoch.
+    pub fn iterations(max_iterations: u64) -> Self {
+        StopRule {
+            norm: Norm::LInf,
+            tolerance: None,
+            max_iterations: Some(max_iterations),
+        }
+    }
+
+    /// Iterates until the residual under `norm` reaches `tolerance`.
+    pub fn tolerance(norm: Norm, tolerance: f64) -> Self {
+        StopRule {
+            norm,
+            tolerance: Some(tolerance),
+            max_iterations: None,
+        }
+    }
+
+    /// Additionally stops after `max_iterations`, even if the tolerance is not reached yet.
+    pub fn with_max_iterations(mut self, max_iterations: u64) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    /// Whether another iteration is needed after `iteration` iterations ended with `residual`.
+    pub fn should_continue(&self, iteration: u64, residual: &Residual) -> bool {
+        if let Some(max_iterations) = self.max_iterations {
+            if iteration >= max_iterations {
+                return false;
+            }
+        }
+
+        match self.tolerance {
+            // the first iteration only distributes the ranks, there is nothing to compare with yet
+            Some(tolerance) => iteration == 0 || residual.value(self.norm) > tolerance,
+            None => true,
+        }
+    }
+}
+
+/// How the ranks are extrapolated every `period` iterations of an epoch, to get closer to where
+/// they converge to in fewer iterations. The stop rule still decides when to stop, on the residual
+/// of the extrapolated ranks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Extrapolation {
+    #[default]
+    None,
+    /// Aitken's delta-squared process on the last three ranks of every vertex by itself, which
+    /// only pays off once a single eigenvector is left in the error
+    Aitken { period: u64 },
+    /// quadratic extrapolation (Kamvar et al., 2003) of the last four rank vectors as a whole,
+    /// which removes the two eigenvectors closest to the solution from the error. Needs the sums
+    /// of `Gram` over all workers, which arrive one iteration later
+    Quadratic { period: u64 },
+}
+
+impl FromStr for Extrapolation {
+    type Err = String;
+
+    /// Parses `none`, `aitken:<period>` or `quadratic:<period>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, param) = match s.split_once(':') {
+            Some((kind, param)) => (kind, Some(param)),
+            None => (s, None),
+        };
+        let period = || {
+            param
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|period| *period >= 2)
+                .ok_or_else(|| format!("extrapolation '{}' needs a period of at least 2", s))
+        };
+        match kind.to_lowercase().as_str() {
+            "none" if param.is_none() => Ok(Extrapolation::None),
+            "aitken" => Ok(Extrapolation::Aitken { period: period()? }),
+            "quadratic" => Ok(Extrapolation::Quadratic { period: period()? }),
+            _ => Err(format!(
+                "unknown extrapolation '{}', expected one of: none, aitken:<period>, \
+                 quadratic:<period>",
+                s
+            )),
+        }
+    }
+}
+
+impl Extrapolation {
+    /// Whether the ranks of `iteration` are extrapolated, once there are enough earlier ones.
+    pub fn applies(&self, iteration: u64) -> bool {
+        match *self {
+            Extrapolation::None => false,
+            Extrapolation::Aitken { period } => iteration >= 2 && iteration.is_multiple_of(period),
+            Extrapolation::Quadratic { period } => {
+                iteration >= 4 && iteration.is_multiple_of(period)
+            }
+        }
+    }
+
+    /// Whether the ranks of `iteration` are added up into a `Gram` for the next one to apply
+    /// quadratic extrapolation.
+    pub fn needs_gram(&self, iteration: u64) -> bool {
+        matches!(self, Extrapolation::Quadratic { .. }) && self.applies(iteration + 1)
+    }
+}
+
+/// Aitken's delta-squared extrapolation of the ranks `x0`, `x1` and `x2` of a vertex in three
+/// iterations in a row, or `x2` where the steps between them do not shrink geometrically.
+pub fn aitken(x0: f64, x1: f64, x2: f64) -> f64 {
+    let ratio = (x2 - x1) / (x1 - x0);
+    if ratio.abs() < 1.0 {
+        x2 + (x2 - x1) * ratio / (1.0 - ratio)
+    } else {
+        x2
+    }
+}
+
+/// Partial sums of quadratic extrapolation over the ranks `x[0..4]` of four iterations in a row,
+/// the products of the differences `y[i] = x[i + 1] - x[0]` of every vertex. Sums computed by
+/// different workers are merged before the coefficients are taken, like `Residual`.
+#[derive(Abomonation, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Gram {
+    pub y0y0: f64,
+    pub y0y1: f64,
+    pub y1y1: f64,
+    pub y0y2: f64,
+    pub y1y2: f64,
+}
+
+impl Gram {
+    /// Accounts for one vertex whose ranks were `x` in the last four iterations.
+    pub fn add(&mut self, x: [f64; 4]) {
+        let y = [x[1] - x[0], x[2] - x[0], x[3] - x[0]];
+        self.y0y0 += y[0] * y[0];
+        self.y0y1 += y[0] * y[1];
+        self.y1y1 += y[1] * y[1];
+        self.y0y2 += y[0] * y[2];
+        self.y1y2 += y[1] * y[2];
+    }
+
+    pub fn merge(&mut self, other: &Gram) {
+        self.y0y0 += other.y0y0;
+        self.y0y1 += other.y0y1;
+        self.y1y1 += other.y1y1;
+        self.y0y2 += other.y0y2;
+        self.y1y2 += other.y1y2;
+    }
+
+    /// The weights of the ranks of the last three iterations in the extrapolated ranks, summing up
+    /// to 1, or `None` if the differences are too close to linearly dependent to solve for them.
+    /// They hold for any three iterations in a row as long as the same eigenvectors are left.
+    pub fn coefficients(&self) -> Option<[f64; 3]> {
+        // least squares solution of gamma0 * y0 + gamma1 * y1 = -y2
+        let det = self.y0y0 * self.y1y1 - self.y0y1 * self.y0y1;
+        if det.is_nan() || det <= 1e-12 * self.y0y0 * self.y1y1 {
+            return None;
+        }
+        let gamma0 = -(self.y1y1 * self.y0y2 - self.y0y1 * self.y1y2) / det;
+        let gamma1 = -(self.y0y0 * self.y1y2 - self.y0y1 * self.y0y2) / det;
+
+        // divide the characteristic polynomial by (lambda - 1), which leaves the solution
+        let beta = [gamma0 + gamma1 + 1.0, gamma1 + 1.0, 1.0];
+        let sum: f64 = beta.iter().sum();
+        if sum.abs() > 1e-12 && sum.is_finite() {
+            Some([beta[0] / sum, beta[1] / sum, beta[2] / sum])
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,1226 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use abomonation_derive::Abomonation;
+use serde_json::json;
+use timely::dataflow::channels::pact::{Exchange, Pipeline};
+use timely::dataflow::operators::generic::OutputHandle;
+use timely::dataflow::{operators::*, Scope, Stream};
+use timely::order::Product;
+use timely::{Data, ExchangeData};
+
+use crate::blocks;
+use crate::convergence::{self, Extrapolation, Gram, Residual, StopRule};
+use crate::telemetry::IterationStats;
+
+/// Where the ranks of an epoch start from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarmStart {
+    /// every vertex starts at 1.0 again, as if the epoch was the first one
+    Cold,
+    /// vertices keep the ranks of the previous epoch, new vertices start at 1.0
+    Previous,
+    /// vertices keep the ranks of the previous epoch, new vertices start at the mean rank of all
+    /// vertices
+    Mean,
+    /// vertices keep the ranks of the previous epoch, new vertices start at the teleport value,
+    /// the rank of a vertex nobody cites
+    Teleport,
+}
+
+impl FromStr for WarmStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cold" => Ok(WarmStart::Cold),
+            "previous" | "warm" => Ok(WarmStart::Previous),
+            "mean" => Ok(WarmStart::Mean),
+            "teleport" => Ok(WarmStart::Teleport),
+            _ => Err(format!(
+                "unknown warm start '{}', expected one of: cold, previous, mean, teleport",
+                s
+            )),
+        }
+    }
+}
+
+/// What the rank of a vertex stands for. All of them add up what the citing vertices contribute in
+/// every iteration, and differ in what a vertex gets on top and how much of its rank it passes on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Centrality {
+    /// the teleport value plus the damped ranks of the citing vertices, each one split among all
+    /// the vertices it cites
+    PageRank,
+    /// `beta` plus `alpha` times the whole ranks of the citing vertices, which converges as long
+    /// as `alpha` is below the inverse of the largest eigenvalue of the graph, on any citation
+    /// graph without cycles
+    Katz { alpha: f64, beta: f64 },
+    /// the rank of the vertex itself plus the whole ranks of the citing vertices, all divided by
+    /// the sum of the ranks, the power iteration of NetworkX. Without cycles the ranks gather at
+    /// the oldest ends of the longest citation chains, and converge slowly
+    Eigenvector,
+    /// PageRank whose teleport value decays as `exp(-age / tau)` with the age of the vertex in
+    /// epochs, as in CiteRank, so that recent vertices cited by other recent ones rank higher
+    CiteRank { tau: f64 },
+}
+
+/// How much a citation weighs, by the number of epochs since it was added. A vertex splits its rank
+/// among the vertices it cites in proportion to the weights of its citations, a citation of the
+/// current epoch weighs 1.0 whatever the decay.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Decay {
+    /// every citation weighs the same however old it is
+    None,
+    /// citations weigh `exp(-age / tau)`
+    Exponential { tau: f64 },
+    /// citations weigh `(1 + age)^-exponent`
+    Power { exponent: f64 },
+    /// citations of the last `epochs` epochs weigh 1.0, older ones nothing
+    Window { epochs: u64 },
+}
+
+impl Decay {
+    /// The weight of a citation added `age` epochs ago.
+    pub fn weight(&self, age: u64) -> f64 {
+        let age = age as f64;
+        match *self {
+            Decay::None => 1.0,
+            Decay::Exponential { tau } => (-age / tau).exp(),
+            Decay::Power { exponent } => (1.0 + age).powf(-exponent),
+            Decay::Window { epochs } if age < epochs as f64 => 1.0,
+            Decay::Window { .. } => 0.0,
+        }
+    }
+}
+
+impl FromStr for Decay {
+    type Err = String;
+
+    /// Parses `none`, `exponential:<tau>`, `power:<exponent>` or `window:<epochs>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, param) = match s.split_once(':') {
+            Some((kind, param)) => (kind, Some(param)),
+            None => (s, None),
+        };
+        let value = |name: &str| {
+            param
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|value| *value > 0.0 && value.is_finite())
+                .ok_or_else(|| format!("decay '{}' needs a positive {}", s, name))
+        };
+        match kind.to_lowercase().as_str() {
+            "none" if param.is_none() => Ok(Decay::None),
+            "exponential" | "exp" => Ok(Decay::Exponential { tau: value("tau")? }),
+            "power" => Ok(Decay::Power {
+                exponent: value("exponent")?,
+            }),
+            "window" => match value("number of epochs")? {
+                epochs if epochs.fract() == 0.0 => Ok(Decay::Window {
+                    epochs: epochs as u64,
+                }),
+                _ => Err(format!("decay '{}' needs a whole number of epochs", s)),
+            },
+            _ => Err(format!(
+                "unknown decay '{}', expected one of: none, exponential:<tau>, \
+                 power:<exponent>, window:<epochs>",
+                s
+            )),
+        }
+    }
+}
+
+/// In which order the ranks of an iteration are updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Schedule {
+    /// every rank is updated from the contributions of the previous iteration only
+    Jacobi,
+    /// every worker updates the ranks it holds one after another, from the highest vertex down, and
+    /// passes the change of each rank on to the vertices it cites on the same worker right away,
+    /// so that they are updated from it within the same iteration. Contributions to other workers
+    /// still arrive in the next iteration. Eigenvector centrality, whose contributions are divided
+    /// by the sum of the ranks of all workers, is always updated like `Jacobi`
+    GaussSeidel,
+    /// the ranks are calculated one strongly connected block of the citation graph after another,
+    /// see `blocks::block_pagerank`, which neither extrapolates nor keeps a `State`. Worker 0
+    /// holds the whole graph and finds its blocks anew every epoch by itself, while the other
+    /// workers wait, so this does not scale with the workers the way the other schedules do.
+    /// Eigenvector centrality is updated like `Jacobi` here as well
+    Blocks,
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jacobi" | "synchronous" => Ok(Schedule::Jacobi),
+            "gauss-seidel" | "gs" => Ok(Schedule::GaussSeidel),
+            "blocks" | "scc" => Ok(Schedule::Blocks),
+            _ => Err(format!(
+                "unknown schedule '{}', expected jacobi, gauss-seidel or blocks",
+                s
+            )),
+        }
+    }
+}
+
+/// Which vertices a random surfer jumps to instead of following a citation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Teleport {
+    /// any vertex, each one getting the whole teleport value
+    #[default]
+    Uniform,
+    /// only the given seed vertices, which get the whole teleport value while all the others get
+    /// nothing, so that ranks measure how close the vertices are to the seeds, as in TrustRank
+    Seeds(Arc<HashSet<usize>>),
+}
+
+impl Teleport {
+    pub fn seeds(seeds: impl IntoIterator<Item = usize>) -> Self {
+        Teleport::Seeds(Arc::new(seeds.into_iter().collect()))
+    }
+
+    /// The share of the teleport value that goes to `vert`.
+    pub fn share(&self, vert: usize) -> f64 {
+        match self {
+            Teleport::Uniform => 1.0,
+            Teleport::Seeds(seeds) if seeds.contains(&vert) => 1.0,
+            Teleport::Seeds(_) => 0.0,
+        }
+    }
+}
+
+/// How `pagerank` ranks the vertices: when an epoch stops iterating, where it starts from, and how
+/// likely a random surfer follows a citation rather than jumping to any vertex.
+#[derive(Clone, Copy, Debug)]
+pub struct Params {
+    pub stop_rule: StopRule,
+    pub warm_start: WarmStart,
+    pub damping: f64,
+    pub centrality: Centrality,
+    pub decay: Decay,
+    pub schedule: Schedule,
+    pub extrapolation: Extrapolation,
+}
+
+impl Params {
+    /// Starts every epoch from the ranks of the previous one, with the usual damping of 0.85.
+    pub fn new(stop_rule: StopRule) -> Self {
+        Params {
+            stop_rule,
+            warm_start: WarmStart::Previous,
+            damping: 0.85,
+            centrality: Centrality::PageRank,
+            decay: Decay::None,
+            schedule: Schedule::Jacobi,
+            extrapolation: Extrapolation::None,
+        }
+    }
+
+    pub fn with_warm_start(mut self, warm_start: WarmStart) -> Self {
+        self.warm_start = warm_start;
+        self
+    }
+
+    pub fn with_damping(mut self, damping: f64) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    pub fn with_centrality(mut self, centrality: Centrality) -> Self {
+        self.centrality = centrality;
+        self
+    }
+
+    pub fn with_decay(mut self, decay: Decay) -> Self {
+        self.decay = decay;
+        self
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /// The rank of a new vertex nobody cites, which eigenvector centrality does not have, so that
+    /// `WarmStart::Teleport` starts new vertices at 1.0 there.
+    pub fn teleport(&self) -> f64 {
+        self.teleport_at(0)
+    }
+
+    /// The rank of a vertex nobody cites, `age` epochs after it first appeared.
+    pub fn teleport_at(&self, age: u64) -> f64 {
+        match self.centrality {
+            Centrality::PageRank => 1.0 - self.damping,
+            Centrality::Katz { beta, .. } => beta,
+            Centrality::Eigenvector => 0.0,
+            Centrality::CiteRank { tau } => (1.0 - self.damping) * (-(age as f64) / tau).exp(),
+        }
+    }
+
+    /// Where a new vertex starts from under `WarmStart::Teleport`.
+    pub(crate) fn teleport_start(&self) -> f64 {
+        match self.centrality {
+            Centrality::Eigenvector => 1.0,
+            _ => self.teleport(),
+        }
+    }
+
+    /// The rank of a vertex `age` epochs after it first appeared, out of the sum of what the
+    /// vertices citing it contribute and its `share` of the teleport value, see `Teleport`.
+    pub(crate) fn rank(&self, contribution: f64, age: u64, share: f64) -> f64 {
+        let weight = match self.centrality {
+            Centrality::PageRank | Centrality::CiteRank { .. } => self.damping,
+            Centrality::Katz { alpha, .. } => alpha,
+            Centrality::Eigenvector => 1.0,
+        };
+        self.teleport_at(age) * share + weight * contribution
+    }
+
+    /// What a vertex of the given `rank` contributes to a vertex it cites with the given
+    /// `weight`, out of the `total_weight` of all its citations.
+    pub(crate) fn contribution(&self, rank: f64, weight: f64, total_weight: f64) -> f64 {
+        match self.centrality {
+            Centrality::PageRank | Centrality::CiteRank { .. } if total_weight > 0.0 => {
+                rank * weight / total_weight
+            }
+            Centrality::PageRank | Centrality::CiteRank { .. } => 0.0,
+            Centrality::Katz { .. } | Centrality::Eigenvector => rank * weight,
+        }
+    }
+
+    /// Whether every vertex contributes to its own rank as well, and all contributions are divided
+    /// by the sum of the ranks they come from.
+    pub(crate) fn normalizes(&self) -> bool {
+        self.centrality == Centrality::Eigenvector
+    }
+
+    /// Whether every worker passes the change of a rank on to the vertices it holds within the
+    /// same iteration, see `Schedule::GaussSeidel`.
+    pub(crate) fn sweeps(&self) -> bool {
+        self.schedule == Schedule::GaussSeidel && !self.normalizes()
+    }
+}
+
+/// Size of a `(src, dst, contribution)` record as it travels between workers.
+const CONTRIBUTION_BYTES: u64 = std::mem::size_of::<(usize, usize, f64)>() as u64;
+
+/// Records entering the loop of the `PageRank` operator at the start of an epoch.
+#[derive(Abomonation, Clone, Debug)]
+enum Change {
+    /// a vertex for the worker owning it, along with a vertex it cites, if any
+    Vertex(usize, Option<usize>),
+    /// the start of an epoch, which every worker takes part in, with or without new vertices
+    Epoch,
+}
+
+/// Records going around the loop of the `PageRank` operator.
+#[derive(Abomonation, Clone, Debug)]
+enum Message {
+    /// a `(src, dst, contribution)` to be summed up by the worker owning `dst`
+    Contribution(usize, usize, f64),
+    /// the global `Progress` of an iteration for worker `index` to extrapolate from in the next
+    /// one, which every worker holding vertices sends to itself empty for `converge` to fill in,
+    /// so that it takes part in the next iteration even if no contribution arrives
+    Progress(usize, Progress),
+    /// the global `Progress` of the last iteration of an epoch for worker `index`, which starts no
+    /// iteration, but tells the next epoch the mean rank
+    Finished(usize, Progress),
+}
+
+impl Message {
+    fn owner(&self) -> usize {
+        match *self {
+            Message::Contribution(_, dst, _) => dst,
+            Message::Progress(index, _) | Message::Finished(index, _) => index,
+        }
+    }
+}
+
+/// Records produced by the `PageRank` operator in each iteration.
+#[derive(Clone, Debug)]
+enum Update {
+    Message(Message),
+    Progress(Progress),
+}
+
+/// How much the ranks held by one worker changed in this iteration, how many contributions it sent
+/// to other workers, its share of the `Gram` sums when the next iteration extrapolates, and the sum
+/// and number of the ranks it holds.
+#[derive(Abomonation, Clone, Copy, Debug, Default)]
+struct Progress {
+    residual: Residual,
+    exchanged: u64,
+    gram: Gram,
+    ranks: (f64, u64),
+}
+
+/// The graph and ranks one worker holds between epochs: the cited vertices of every vertex it owns
+/// along with the epoch each citation was added in, the rank of every vertex it owns as of the last
+/// iteration, and the epoch it first appeared in. `total` is the sum and number of the ranks of all
+/// workers, which `WarmStart::Mean` starts new vertices from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct State {
+    pub edges: HashMap<usize, Vec<(usize, u64)>>,
+    pub ranks: HashMap<usize, f64>,
+    pub born: HashMap<usize, u64>,
+    pub total: (f64, u64),
+}
+
+/// Calculates the PageRank of each epoch of `edges`, a stream of new `(src, dst)` citations per
+/// outer timestamp, starting each epoch as `params.warm_start` says and iterating until
+/// `params.stop_rule` says otherwise. Returns the rank of every vertex at the end of every epoch,
+/// on the worker owning the vertex, and the `IterationStats` of every iteration as seen by worker 0.
+pub fn pagerank<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    pagerank_from(edges, params, Rc::new(RefCell::new(State::default())))
+}
+
+/// Same as `pagerank`, continuing from the `state` of this worker, which holds the state of the
+/// last epoch whose ranks came out, until the edges of the next epoch are fed.
+pub fn pagerank_from<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    state: Rc<RefCell<State>>,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    personalized_pagerank_from(edges, params, Teleport::Uniform, state)
+}
+
+/// Same as `pagerank_from`, with the random surfer jumping to the vertices `teleport` says only.
+pub fn personalized_pagerank_from<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    teleport: Teleport,
+    state: Rc<RefCell<State>>,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    if params.schedule == Schedule::Blocks && !params.normalizes() {
+        return blocks::block_pagerank(edges, params, teleport);
+    }
+
+    let Params {
+        stop_rule,
+        warm_start,
+        ..
+    } = params;
+
+    // every vertex is owned by exactly one worker: an edge goes to the owner of its `src`, and its
+    // `dst` is announced to the owner of `dst`, which is where contributions to `dst` are sent to
+    let vertices =
+        edges.flat_map(|(src, dst)| [Change::Vertex(src, Some(dst)), Change::Vertex(dst, None)]);
+
+    // every worker takes part in every epoch, even without any new vertex of its own
+    let epochs = edges
+        .unary(Pipeline, "Epochs", |_capability, _info| {
+            let mut edge_vec = Vec::new();
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut edge_vec);
+                    edge_vec.clear();
+                    output.session(&time).give(Change::Epoch);
+                });
+            }
+        })
+        .broadcast();
+
+    // the final ranks depend on how old the vertices are
+    let final_state = state.clone();
+    let final_teleport = teleport.clone();
+
+    let mut scope = edges.scope();
+    let (contributions, stats) = scope.iterative::<u64, _, _>(|subscope| {
+        // create a new loop stream
+        let (handle, contribution_stream) = subscope.loop_variable(1);
+        let (index, peers) = (subscope.index(), subscope.peers());
+
+        let updates = vertices.concat(&epochs).enter(subscope).binary_frontier(
+            &contribution_stream,
+            Exchange::new(move |change: &Change| match change {
+                Change::Vertex(vert, _) => *vert as u64,
+                Change::Epoch => index as u64,
+            }),
+            Exchange::new(|message: &Message| message.owner() as u64),
+            "PageRank",
+            move |_capability, _info| {
+                // where we stash out-of-order data
+                let mut edge_stash = HashMap::new();
+                let mut contribution_stash = HashMap::new();
+
+                // empty list for swapping
+                let mut edge_vec = Vec::new();
+                let mut contribution_vec = Vec::new();
+
+                // the ranks of the three iterations before the last one, to extrapolate from
+                let mut history = HashMap::new();
+
+                move |input1, input2, output| {
+                    // accumulative edges and ranks
+                    let mut state = state.borrow_mut();
+                    let State {
+                        edges,
+                        ranks,
+                        born,
+                        total,
+                    } = &mut *state;
+
+                    // hold on to edge changes until it is time
+                    input1.for_each(|time, data| {
+                        data.swap(&mut edge_vec);
+                        edge_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut edge_vec);
+                    });
+
+                    // hold on to incoming contributions until it is time, but keep what the last
+                    // iteration of an epoch ended with right away, as it starts no iteration
+                    input2.for_each(|time, data| {
+                        data.swap(&mut contribution_vec);
+                        contribution_vec.retain(|message| match message {
+                            Message::Finished(_, global) => {
+                                *total = global.ranks;
+                                false
+                            }
+                            _ => true,
+                        });
+                        if !contribution_vec.is_empty() {
+                            contribution_stash
+                                .entry(time.retain())
+                                .or_insert(Vec::new())
+                                .append(&mut contribution_vec);
+                        }
+                    });
+
+                    let frontiers = &[input1.frontier(), input2.frontier()];
+
+                    for (time, edge_changes) in edge_stash.iter_mut() {
+                        // ranks are shared by all epochs, wait until earlier epochs stop iterating
+                        let earlier_epochs_done = input2
+                            .frontier()
+                            .frontier()
+                            .iter()
+                            .all(|t| t.outer >= time.outer);
+
+                        if frontiers.iter().all(|f| !f.less_equal(time)) && earlier_epochs_done {
+                            let mut session = output.session(time);
+
+                            // decide where known vertices and new vertices start from
+                            let initial_rank = match warm_start {
+                                WarmStart::Cold => {
+                                    for rank in ranks.values_mut() {
+                                        *rank = 1.0f64;
+                                    }
+                                    1.0f64
+                                }
+                                WarmStart::Previous => 1.0f64,
+                                WarmStart::Mean if total.1 > 0 => total.0 / (total.1 as f64),
+                                WarmStart::Mean => 1.0f64,
+                                WarmStart::Teleport => params.teleport_start(),
+                            };
+
+                            for change in edge_changes.drain(..) {
+                                // populate all map using received values
+                                if let Change::Vertex(vert, dst) = change {
+                                    ranks.entry(vert).or_insert(initial_rank);
+                                    born.entry(vert).or_insert(time.outer);
+                                    if let Some(dst) = dst {
+                                        let dsts = edges.entry(vert).or_insert(Vec::new());
+                                        dsts.push((dst, time.outer));
+                                    }
+                                }
+                            }
+
+                            // nothing to compare with yet, but this marks the start of the epoch,
+                            // and the sum of the ranks scales the first contributions
+                            let mut progress = Progress::default();
+                            for rank in ranks.values() {
+                                progress.residual.add(*rank, *rank);
+                                progress.ranks.0 += rank;
+                            }
+                            progress.ranks.1 = ranks.len() as u64;
+                            history.clear();
+                            if params.extrapolation != Extrapolation::None {
+                                history
+                                    .extend(ranks.iter().map(|(vert, rank)| (*vert, [*rank; 3])));
+                            }
+
+                            // distribute contributions for next iteration
+                            for (src, dst, contribution) in
+                                distribute(&params, edges, ranks, time.outer)
+                            {
+                                progress.exchanged += u64::from(dst % peers != index);
+                                session.give(Update::Message(Message::Contribution(
+                                    src,
+                                    dst,
+                                    contribution,
+                                )));
+                            }
+                            if !ranks.is_empty() {
+                                session.give(Update::Message(Message::Progress(
+                                    index,
+                                    Progress::default(),
+                                )));
+                            }
+                            session.give(Update::Progress(progress));
+                        }
+                    }
+                    edge_stash.retain(|_key, val| !val.is_empty());
+
+                    for (time, contributions) in contribution_stash.iter_mut() {
+                        if frontiers.iter().all(|f| !f.less_equal(time)) {
+                            let mut session = output.session(time);
+
+                            let mut contribution_sum = HashMap::new();
+                            let mut global = None;
+                            for message in contributions.drain(..) {
+                                match message {
+                                    Message::Contribution(_, dst, contribution) => {
+                                        *contribution_sum.entry(dst).or_insert(0.0f64) +=
+                                            contribution;
+                                    }
+                                    Message::Progress(_, progress) => global = Some(progress),
+                                    Message::Finished(..) => {}
+                                }
+                            }
+
+                            // calculate the new rank for this iteration, citing vertices first
+                            // when sweeping, as papers mostly cite older papers with lower ids
+                            let mut order = ranks.keys().copied().collect::<Vec<_>>();
+                            if params.sweeps() {
+                                order.sort_unstable_by(|a, b| b.cmp(a));
+                            }
+                            let mut new_ranks = HashMap::new();
+                            let mut weights = Vec::new();
+                            for vert in order {
+                                let old_rank = ranks[&vert];
+                                let contribution = contribution_sum.get(&vert).unwrap_or(&0.0f64);
+                                let age = age(born, vert, time.outer);
+                                let share = teleport.share(vert);
+                                let new_rank = params.rank(*contribution, age, share);
+                                new_ranks.insert(vert, new_rank);
+
+                                // the vertices on this worker it cites see the change right away
+                                if let (true, Some(dsts)) = (params.sweeps(), edges.get(&vert)) {
+                                    let total_weight =
+                                        citation_weights(&params, dsts, time.outer, &mut weights);
+                                    for ((dst, _), weight) in dsts.iter().zip(&weights) {
+                                        if dst % peers == index {
+                                            let change = params.contribution(
+                                                new_rank - old_rank,
+                                                *weight,
+                                                total_weight,
+                                            );
+                                            *contribution_sum.entry(*dst).or_insert(0.0f64) +=
+                                                change;
+                                        }
+                                    }
+                                }
+                            }
+
+                            // jump ahead from the ranks of earlier iterations every now and then
+                            let iteration = time.inner;
+                            let coefficients = global.and_then(|global| global.gram.coefficients());
+                            extrapolate(
+                                params.extrapolation,
+                                iteration,
+                                coefficients,
+                                &history,
+                                ranks,
+                                &mut new_ranks,
+                            );
+
+                            let mut progress = Progress::default();
+                            let needs_gram = params.extrapolation.needs_gram(iteration);
+                            for (vert, new_rank) in new_ranks.iter() {
+                                let old_rank = ranks[vert];
+                                progress.residual.add(*new_rank, old_rank);
+                                progress.ranks.0 += new_rank;
+                                if let Some(earlier) = history.get_mut(vert) {
+                                    if needs_gram {
+                                        progress
+                                            .gram
+                                            .add([earlier[1], earlier[2], old_rank, *new_rank]);
+                                    }
+                                    *earlier = [earlier[1], earlier[2], old_rank];
+                                }
+                            }
+
+                            // distribute contributions for next iteration
+                            progress.ranks.1 = new_ranks.len() as u64;
+                            *ranks = new_ranks;
+                            for (src, dst, contribution) in
+                                distribute(&params, edges, ranks, time.outer)
+                            {
+                                progress.exchanged += u64::from(dst % peers != index);
+                                session.give(Update::Message(Message::Contribution(
+                                    src,
+                                    dst,
+                                    contribution,
+                                )));
+                            }
+                            session.give(Update::Message(Message::Progress(
+                                index,
+                                Progress::default(),
+                            )));
+                            session.give(Update::Progress(progress));
+                        }
+                    }
+                    contribution_stash.retain(|_key, val| !val.is_empty());
+                }
+            },
+        );
+
+        let messages = updates.flat_map(|update| match update {
+            Update::Message(message) => Some(message),
+            Update::Progress(..) => None,
+        });
+        let local_progress = updates.flat_map(|update| match update {
+            Update::Progress(progress) => Some(progress),
+            Update::Message(..) => None,
+        });
+
+        let global_progress = all_reduce(&local_progress);
+        let stats = telemetry(&global_progress, index);
+        let (continuing, leaving) = converge(
+            &messages,
+            &global_progress,
+            stop_rule,
+            move |global: &Progress, message| match message {
+                Message::Contribution(src, dst, contribution)
+                    if params.normalizes() && global.residual.rank_sum > 0.0 =>
+                {
+                    Message::Contribution(src, dst, contribution / global.residual.rank_sum)
+                }
+                Message::Progress(worker, _) => Message::Progress(worker, *global),
+                message => message,
+            },
+        );
+
+        // continue the iteration because the stop rule does not hold yet, and let every worker
+        // know how the last iteration ended once it holds
+        let finished = leaving.flat_map(|message| match message {
+            Message::Progress(worker, global) => Some(Message::Finished(worker, global)),
+            _ => None,
+        });
+        continuing.concat(&finished).connect_loop(handle);
+
+        // break out the iteration because the stop rule holds
+        let leaving = leaving.flat_map(|message| match message {
+            Message::Contribution(src, dst, contribution) => Some((src, dst, contribution)),
+            Message::Progress(..) | Message::Finished(..) => None,
+        });
+        (leaving.leave(), stats.leave())
+    });
+
+    (
+        final_ranks(&contributions, params, final_teleport, final_state),
+        stats,
+    )
+}
+
+/// How many epochs before `epoch` the vertex `vert` first appeared, 0 if it is not known.
+fn age(born: &HashMap<usize, u64>, vert: usize, epoch: u64) -> u64 {
+    born.get(&vert)
+        .map_or(0, |born| epoch.saturating_sub(*born))
+}
+
+/// The `(src, dst, contribution)` of every vertex in `ranks` to every vertex it cites, and to
+/// itself where `params` say so.
+fn distribute(
+    params: &Params,
+    edges: &HashMap<usize, Vec<(usize, u64)>>,
+    ranks: &HashMap<usize, f64>,
+    epoch: u64,
+) -> Vec<(usize, usize, f64)> {
+    let mut contributions = Vec::new();
+    let mut weights = Vec::new();
+    for (src, rank) in ranks.iter() {
+        if params.normalizes() {
+            contributions.push((*src, *src, *rank));
+        }
+        if let Some(dsts) = edges.get(src) {
+            let total_weight = citation_weights(params, dsts, epoch, &mut weights);
+            for ((dst, _), weight) in dsts.iter().zip(&weights) {
+                let contribution = params.contribution(*rank, *weight, total_weight);
+                contributions.push((*src, *dst, contribution));
+            }
+        }
+    }
+    contributions
+}
+
+/// Replaces the `new_ranks` of `iteration` by their extrapolation from the `ranks` of the iteration
+/// before and the `history` of the ones before that, if `extrapolation` applies to it. Quadratic
+/// extrapolation takes the `coefficients` of the global `Gram` of the iteration before, and leaves
+/// the ranks as they are without them.
+fn extrapolate(
+    extrapolation: Extrapolation,
+    iteration: u64,
+    coefficients: Option<[f64; 3]>,
+    history: &HashMap<usize, [f64; 3]>,
+    ranks: &HashMap<usize, f64>,
+    new_ranks: &mut HashMap<usize, f64>,
+) {
+    if !extrapolation.applies(iteration) {
+        return;
+    }
+    for (vert, new_rank) in new_ranks.iter_mut() {
+        let (x0, x1) = match (history.get(vert), ranks.get(vert)) {
+            (Some(earlier), Some(old_rank)) => (earlier[2], *old_rank),
+            _ => continue,
+        };
+        *new_rank = match (extrapolation, coefficients) {
+            (Extrapolation::Aitken { .. }, _) => convergence::aitken(x0, x1, *new_rank),
+            (Extrapolation::Quadratic { .. }, Some([b0, b1, b2])) => {
+                b0 * x0 + b1 * x1 + b2 * *new_rank
+            }
+            _ => *new_rank,
+        };
+    }
+}
+
+/// Puts the weight of each of the citations `dsts` as of `epoch` into `weights`, as citations weigh
+/// less the older they are, and returns their sum.
+fn citation_weights(
+    params: &Params,
+    dsts: &[(usize, u64)],
+    epoch: u64,
+    weights: &mut Vec<f64>,
+) -> f64 {
+    weights.clear();
+    weights.extend(
+        dsts.iter()
+            .map(|(_, added)| params.decay.weight(epoch.saturating_sub(*added))),
+    );
+    weights.iter().sum()
+}
+
+/// What every worker adds up in each iteration, and what all of them need in full to decide whether
+/// to continue: how much the ranks it holds changed, and how many records it sent to other workers.
+pub(crate) trait Aggregate: ExchangeData + Copy + Default {
+    fn merge(&mut self, other: &Self);
+
+    fn residual(&self) -> Residual;
+
+    fn exchanged(&self) -> u64;
+}
+
+impl Aggregate for Progress {
+    fn merge(&mut self, other: &Self) {
+        self.residual.merge(&other.residual);
+        self.exchanged += other.exchanged;
+        self.gram.merge(&other.gram);
+        self.ranks.0 += other.ranks.0;
+        self.ranks.1 += other.ranks.1;
+    }
+
+    fn residual(&self) -> Residual {
+        self.residual
+    }
+
+    fn exchanged(&self) -> u64 {
+        self.exchanged
+    }
+}
+
+impl Aggregate for (Residual, u64) {
+    fn merge(&mut self, other: &Self) {
+        self.0.merge(&other.0);
+        self.1 += other.1;
+    }
+
+    fn residual(&self) -> Residual {
+        self.0
+    }
+
+    fn exchanged(&self) -> u64 {
+        self.1
+    }
+}
+
+/// All-reduce: every worker receives the `local` aggregate of every worker, and merges them into the
+/// global aggregate of each iteration, so each one can decide by itself whether to continue. They
+/// are merged in the order of the workers, so that all of them end up with the very same aggregate,
+/// whatever order they arrived in.
+pub(crate) fn all_reduce<S, A>(local: &Stream<S, A>) -> Stream<S, A>
+where
+    S: Scope<Timestamp = Product<u64, u64>>,
+    A: Aggregate,
+{
+    let index = local.scope().index();
+    local
+        .map(move |aggregate| (index, aggregate))
+        .broadcast()
+        .unary_frontier(Pipeline, "GlobalResidual", |_capability, _info| {
+            let mut aggregate_stash = HashMap::new();
+            let mut aggregate_vec = Vec::new();
+
+            move |input, output| {
+                // hold on to the aggregates of all workers until every one arrived
+                input.for_each(|time, data| {
+                    data.swap(&mut aggregate_vec);
+                    aggregate_stash
+                        .entry(time.retain())
+                        .or_insert_with(Vec::new)
+                        .append(&mut aggregate_vec);
+                });
+
+                for (time, aggregates) in aggregate_stash.iter_mut() {
+                    if !input.frontier().less_equal(time) {
+                        // stable, so the aggregates of a worker stay in the order it sent them
+                        aggregates.sort_by_key(|&(index, _)| index);
+                        let mut aggregate = A::default();
+                        for (_, other) in aggregates.iter() {
+                            aggregate.merge(other);
+                        }
+                        output.session(time).give(aggregate);
+                    }
+                }
+                aggregate_stash.retain(|time, _| input.frontier().less_equal(time));
+            }
+        })
+}
+
+/// The `IterationStats` of every iteration, out of the `global` aggregates. Worker `index` 0
+/// reports how the residual decays, the other workers know the same values.
+pub(crate) fn telemetry<S, A>(global: &Stream<S, A>, index: usize) -> Stream<S, IterationStats>
+where
+    S: Scope<Timestamp = Product<u64, u64>>,
+    A: Aggregate,
+{
+    global.unary_frontier(Pipeline, "Telemetry", move |_capability, _info| {
+        let mut epoch_starts = HashMap::new();
+        let mut aggregate_vec = Vec::new();
+
+        move |input, output| {
+            input.for_each(|time, data| {
+                data.swap(&mut aggregate_vec);
+                if index != 0 {
+                    aggregate_vec.clear();
+                    return;
+                }
+
+                let start = *epoch_starts
+                    .entry(time.time().outer)
+                    .or_insert_with(Instant::now);
+                let mut session = output.session(&time);
+                for aggregate in aggregate_vec.drain(..) {
+                    session.give(IterationStats {
+                        iteration: time.time().inner,
+                        residual: aggregate.residual(),
+                        exchanged_bytes: aggregate.exchanged() * CONTRIBUTION_BYTES,
+                        elapsed: start.elapsed().as_secs_f64(),
+                    });
+                }
+            });
+
+            epoch_starts.retain(|epoch, _| {
+                input
+                    .frontier()
+                    .frontier()
+                    .iter()
+                    .any(|time| time.outer <= *epoch)
+            });
+        }
+    })
+}
+
+/// Splits the `messages` of every iteration into those that go around the loop again and those
+/// that leave it, as `stop_rule` decides on the `global` aggregate of the same iteration. Both are
+/// passed through `scale` first, which may adjust them by the global aggregate.
+pub(crate) fn converge<S, D, A, F>(
+    messages: &Stream<S, D>,
+    global: &Stream<S, A>,
+    stop_rule: StopRule,
+    scale: F,
+) -> (Stream<S, D>, Stream<S, D>)
+where
+    S: Scope<Timestamp = Product<u64, u64>>,
+    D: Data,
+    A: Aggregate,
+    F: Fn(&A, D) -> D + 'static,
+{
+    let mut parts = decide(messages, global, stop_rule, scale)
+        .partition(2, |(continues, data)| (continues as u64, data));
+
+    let continuing = parts.pop().unwrap();
+    let leaving = parts.pop().unwrap();
+    (continuing, leaving)
+}
+
+/// Only the `messages` that go around the loop again, for loops that have no use for the messages
+/// left once `stop_rule` holds, see `converge`.
+pub(crate) fn continuing<S, D, A>(
+    messages: &Stream<S, D>,
+    global: &Stream<S, A>,
+    stop_rule: StopRule,
+) -> Stream<S, D>
+where
+    S: Scope<Timestamp = Product<u64, u64>>,
+    D: Data,
+    A: Aggregate,
+{
+    decide(messages, global, stop_rule, |_, message| message).flat_map(|(continues, message)| {
+        if continues {
+            Some(message)
+        } else {
+            None
+        }
+    })
+}
+
+/// The `messages` of every iteration passed through `scale`, each along with whether `stop_rule`
+/// continues on the `global` aggregate of the same iteration.
+fn decide<S, D, A, F>(
+    messages: &Stream<S, D>,
+    global: &Stream<S, A>,
+    stop_rule: StopRule,
+    scale: F,
+) -> Stream<S, (bool, D)>
+where
+    S: Scope<Timestamp = Product<u64, u64>>,
+    D: Data,
+    A: Aggregate,
+    F: Fn(&A, D) -> D + 'static,
+{
+    messages.binary_frontier(
+        global,
+        Pipeline,
+        Pipeline,
+        "Converge",
+        move |_capability, _info| {
+            let mut message_stash = HashMap::new();
+            let mut aggregate_stash = HashMap::new();
+
+            let mut message_vec = Vec::new();
+
+            move |input1, input2, output| {
+                // hold on to local messages until it is time
+                input1.for_each(|time, data| {
+                    data.swap(&mut message_vec);
+                    message_stash
+                        .entry(time.retain())
+                        .or_insert(Vec::new())
+                        .append(&mut message_vec);
+                });
+
+                input2.for_each(|time, data| {
+                    for aggregate in data.iter() {
+                        aggregate_stash.insert(*time.time(), *aggregate);
+                    }
+                });
+
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                for (time, data) in message_stash.iter_mut() {
+                    if frontiers.iter().all(|f| !f.less_equal(time)) {
+                        let aggregate: A = aggregate_stash
+                            .get(time.time())
+                            .cloned()
+                            .unwrap_or_default();
+                        let continues =
+                            stop_rule.should_continue(time.inner, &aggregate.residual());
+
+                        // send out result
+                        let mut session = output.session(time);
+                        for d in data.drain(..) {
+                            session.give((continues, scale(&aggregate, d)));
+                        }
+                    }
+                }
+
+                message_stash.retain(|_key, val| !val.is_empty());
+                aggregate_stash.retain(|time, _| frontiers.iter().any(|f| f.less_equal(time)));
+            }
+        },
+    )
+}
+
+/// Once an epoch left the iterative scope, calculates the final rank of each vertex from the
+/// `contributions` of the last iteration, on the worker owning the vertex and its `state`.
+fn final_ranks<G>(
+    contributions: &Stream<G, (usize, usize, f64)>,
+    params: Params,
+    teleport: Teleport,
+    state: Rc<RefCell<State>>,
+) -> Stream<G, (usize, f64)>
+where
+    G: Scope<Timestamp = u64>,
+{
+    contributions
+        .flat_map(|(src, dst, contribution)| [(src, 0.0f64), (dst, contribution)])
+        .unary_frontier(
+            Exchange::new(|x: &(usize, f64)| x.0 as u64),
+            "Ranks",
+            |_capability, _info| {
+                let mut contribution_stash = HashMap::new();
+                let mut contribution_vec = Vec::new();
+
+                move |input, output| {
+                    // sum up contributions as they arrive
+                    input.for_each(|time, data| {
+                        data.swap(&mut contribution_vec);
+                        let contribution_sum = contribution_stash
+                            .entry(time.retain())
+                            .or_insert(HashMap::new());
+                        for (vert, contribution) in contribution_vec.drain(..) {
+                            *contribution_sum.entry(vert).or_insert(0.0f64) += contribution;
+                        }
+                    });
+
+                    for (time, contribution_sum) in contribution_stash.iter_mut() {
+                        if !input.frontier().less_equal(time) {
+                            // vertices keep the epoch they first appeared in, whatever came since
+                            let state = state.borrow();
+                            let mut session = output.session(time);
+                            for (vert, contribution) in contribution_sum.drain() {
+                                let age = age(&state.born, vert, *time.time());
+                                let share = teleport.share(vert);
+                                session.give((vert, params.rank(contribution, age, share)));
+                            }
+                        }
+                    }
+                    contribution_stash.retain(|_key, val| !val.is_empty());
+                }
+            },
+        )
+}
+
+/// How the top ranks of every epoch are printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// a heading per epoch followed by one line per vertex
+    Text,
+    /// `epoch,position,vertex,rank` rows under a single header
+    Csv,
+    /// one JSON object per epoch holding its top vertices in order
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _ => Err(format!(
+                "unknown format '{}', expected one of: text, csv, json",
+                s
+            )),
+        }
+    }
+}
+
+/// Gathers the `ranks` of each epoch on worker 0, and prints the top `k` of them normalized so that
+/// they sum up to the number of vertices.
+pub fn print_top_ranks<G>(ranks: &Stream<G, (usize, f64)>, k: usize) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    write_top_ranks(ranks, k, Format::Text)
+}
+
+/// Same as `print_top_ranks`, in the given `format`.
+pub fn write_top_ranks<G>(
+    ranks: &Stream<G, (usize, f64)>,
+    k: usize,
+    format: Format,
+) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    write_top_scores(ranks, k, format, "rank")
+}
+
+/// Same as `write_top_ranks`, for scores of any other `measure`, which names them in the output.
+pub fn write_top_scores<G>(
+    scores: &Stream<G, (usize, f64)>,
+    k: usize,
+    format: Format,
+    measure: &'static str,
+) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    scores.unary_frontier(Exchange::new(|_| 0), "Result", move |_capability, _info| {
+        let mut rank_stash = HashMap::new();
+        let mut rank_vec = Vec::new();
+        let mut header_printed = false;
+
+        move |input, _output: &mut OutputHandle<u64, (), _>| {
+            // hold on to final ranks until it is time.
+            input.for_each(|time, data| {
+                data.swap(&mut rank_vec);
+                rank_stash
+                    .entry(time.retain())
+                    .or_insert(Vec::new())
+                    .append(&mut rank_vec);
+            });
+
+            for (time, ranks) in rank_stash.iter_mut() {
+                if !input.frontier.less_equal(time) {
+                    // get normalize factor, nothing to normalize if the seeds of a personalized
+                    // teleport have not appeared yet
+                    let sum: f64 = ranks.iter().map(|(_, rank)| rank).sum();
+                    let normalize_factor = match sum {
+                        sum if sum > 0.0 => ranks.len() as f64 / sum,
+                        _ => 1.0,
+                    };
+
+                    // get top ranks
+                    ranks.sort_by(|&(_, a), &(_, b)| b.partial_cmp(&a).unwrap());
+                    let top = ranks
+                        .iter()
+                        .take(k)
+                        .map(|(vert, rank)| (*vert, rank * normalize_factor));
+                    match format {
+                        Format::Text => {
+                            println!("--- year {:?} top {} ---", time.time(), k);
+                            for (vert, rank) in top {
+                                println!("{:?} has {} score: {:?}", vert, measure, rank);
+                            }
+                        }
+                        Format::Csv => {
+                            if !header_printed {
+                                println!("epoch,position,vertex,{}", measure);
+                                header_printed = true;
+                            }
+                            for (position, (vert, rank)) in top.enumerate() {
+                                println!("{},{},{},{:?}", time.time(), position + 1, vert, rank);
+                            }
+                        }
+                        Format::Json => {
+                            let top = top
+                                .map(|(vert, rank)| json!({ "vertex": vert, measure: rank }))
+                                .collect::<Vec<_>>();
+                            println!("{}", json!({ "epoch": time.time(), "top": top }));
+                        }
+                    }
+                    ranks.clear();
+                }
+            }
+            rank_stash.retain(|_key, val| !val.is_empty());
+        }
+    })
+}
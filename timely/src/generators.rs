@@ -0,0 +1,320 @@
+use std::str::FromStr;
+
+/// Papers and citations of the HepPh dataset, which the synthetic graphs scale from.
+pub const HEP_PH_PAPERS: usize = 34_546;
+pub const HEP_PH_CITATIONS: usize = 421_578;
+/// Years 1992 to 2002 of the HepPh dataset.
+pub const HEP_PH_EPOCHS: usize = 11;
+
+/// Random graph models that produce a stream of new edges per epoch, in the same shape as
+/// `utils::get_citations_from_file`. Every model is an endless iterator over epochs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// each epoch adds vertices and uniformly random edges among all vertices so far
+    ErdosRenyi,
+    /// each new vertex links to existing vertices with a probability proportional to their degree
+    BarabasiAlbert,
+    /// edges fall recursively into the quadrants of the adjacency matrix, giving a skewed
+    /// Kronecker graph over a fixed set of vertices
+    RMat,
+    /// new papers cite earlier papers, either picked at random or copied from the references of
+    /// the paper picked
+    Citation,
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "erdos-renyi" | "er" => Ok(Model::ErdosRenyi),
+            "barabasi-albert" | "ba" => Ok(Model::BarabasiAlbert),
+            "rmat" | "kronecker" => Ok(Model::RMat),
+            "citation" => Ok(Model::Citation),
+            _ => Err(format!(
+                "unknown model '{}', expected one of: erdos-renyi, barabasi-albert, rmat, citation",
+                s
+            )),
+        }
+    }
+}
+
+/// Creates a generator of `model` whose graph has `scale` times the papers and citations of
+/// HepPh spread over the same number of epochs. Each of `shards` workers calls this with its own
+/// `shard` and sends what it gets: Erdős–Rényi and R-MAT split their edges between the shards,
+/// while the other models depend on all earlier edges and are only generated by shard 0.
+pub fn generator(
+    model: Model,
+    scale: f64,
+    seed: u64,
+    shard: usize,
+    shards: usize,
+) -> Box<dyn Iterator<Item = Vec<(usize, usize)>>> {
+    let papers = ((HEP_PH_PAPERS as f64 * scale) as usize / HEP_PH_EPOCHS).max(1);
+    let citations = ((HEP_PH_CITATIONS as f64 * scale) as usize / HEP_PH_EPOCHS).max(1);
+    let citations_per_paper = (citations / papers).max(1);
+
+    // each shard draws its own edges, and together they draw as many as a single one would
+    let shard_seed = seed ^ (shard as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let shard_citations = citations / shards + usize::from(shard < citations % shards);
+
+    match model {
+        Model::ErdosRenyi => Box::new(ErdosRenyi::new(papers, shard_citations, shard_seed)),
+        Model::RMat => {
+            let vertices = papers * HEP_PH_EPOCHS;
+            let scale = usize::BITS - vertices.next_power_of_two().leading_zeros() - 1;
+            Box::new(RMat::new(scale, shard_citations, shard_seed))
+        }
+        Model::BarabasiAlbert if shard == 0 => {
+            Box::new(BarabasiAlbert::new(papers, citations_per_paper, seed))
+        }
+        Model::Citation if shard == 0 => {
+            Box::new(CitationModel::new(papers, citations_per_paper, 0.5, seed))
+        }
+        Model::BarabasiAlbert | Model::Citation => Box::new(std::iter::repeat_with(Vec::new)),
+    }
+}
+
+/// SplitMix64, small and fast, and keeps generated graphs identical across platforms and crate
+/// versions so that benchmarks stay comparable.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform integer in `0..bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    /// A uniform float in `[0, 1)`.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Directed G(n, m): every epoch adds `vertices_per_epoch` vertices and `edges_per_epoch` edges
+/// between uniformly random distinct vertices of all epochs so far.
+pub struct ErdosRenyi {
+    rng: Rng,
+    vertices: usize,
+    vertices_per_epoch: usize,
+    edges_per_epoch: usize,
+}
+
+impl ErdosRenyi {
+    pub fn new(vertices_per_epoch: usize, edges_per_epoch: usize, seed: u64) -> Self {
+        ErdosRenyi {
+            rng: Rng::new(seed),
+            vertices: 0,
+            vertices_per_epoch,
+            edges_per_epoch,
+        }
+    }
+}
+
+impl Iterator for ErdosRenyi {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.vertices += self.vertices_per_epoch;
+        if self.vertices < 2 {
+            return Some(Vec::new());
+        }
+
+        let mut edges = Vec::with_capacity(self.edges_per_epoch);
+        while edges.len() < self.edges_per_epoch {
+            let src = self.rng.below(self.vertices);
+            let dst = self.rng.below(self.vertices);
+            if src != dst {
+                edges.push((src, dst));
+            }
+        }
+        Some(edges)
+    }
+}
+
+/// Preferential attachment: every epoch adds `vertices_per_epoch` vertices, each linking to
+/// `edges_per_vertex` earlier vertices picked with a probability proportional to their degree.
+pub struct BarabasiAlbert {
+    rng: Rng,
+    vertices: usize,
+    vertices_per_epoch: usize,
+    edges_per_vertex: usize,
+    // both endpoints of every edge so far, so a uniform pick is proportional to the degree
+    endpoints: Vec<usize>,
+}
+
+impl BarabasiAlbert {
+    pub fn new(vertices_per_epoch: usize, edges_per_vertex: usize, seed: u64) -> Self {
+        BarabasiAlbert {
+            rng: Rng::new(seed),
+            vertices: 0,
+            vertices_per_epoch,
+            edges_per_vertex,
+            endpoints: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for BarabasiAlbert {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut edges = Vec::with_capacity(self.vertices_per_epoch * self.edges_per_vertex);
+        for src in self.vertices..self.vertices + self.vertices_per_epoch {
+            if src == 0 {
+                continue;
+            }
+            for _ in 0..self.edges_per_vertex {
+                // fall back to a uniform pick until there are edges to attach to
+                let dst = if self.endpoints.is_empty() {
+                    self.rng.below(src)
+                } else {
+                    self.endpoints[self.rng.below(self.endpoints.len())]
+                };
+                edges.push((src, dst));
+            }
+            for (src, dst) in &edges[edges.len() - self.edges_per_vertex..] {
+                self.endpoints.push(*src);
+                self.endpoints.push(*dst);
+            }
+        }
+        self.vertices += self.vertices_per_epoch;
+        Some(edges)
+    }
+}
+
+/// R-MAT over `2^scale` vertices: every edge descends `scale` times into one of the four
+/// quadrants of the adjacency matrix with probabilities `a`, `b`, `c` and `1 - a - b - c`.
+pub struct RMat {
+    rng: Rng,
+    scale: u32,
+    edges_per_epoch: usize,
+    probabilities: (f64, f64, f64),
+}
+
+impl RMat {
+    /// Uses the probabilities of the Graph500 benchmark.
+    pub fn new(scale: u32, edges_per_epoch: usize, seed: u64) -> Self {
+        RMat::with_probabilities(scale, edges_per_epoch, (0.57, 0.19, 0.19), seed)
+    }
+
+    pub fn with_probabilities(
+        scale: u32,
+        edges_per_epoch: usize,
+        probabilities: (f64, f64, f64),
+        seed: u64,
+    ) -> Self {
+        RMat {
+            rng: Rng::new(seed),
+            scale,
+            edges_per_epoch,
+            probabilities,
+        }
+    }
+}
+
+impl Iterator for RMat {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (a, b, c) = self.probabilities;
+
+        let mut edges = Vec::with_capacity(self.edges_per_epoch);
+        while edges.len() < self.edges_per_epoch {
+            let (mut src, mut dst) = (0, 0);
+            for _ in 0..self.scale {
+                let p = self.rng.unit();
+                let (row, col) = if p < a {
+                    (0, 0)
+                } else if p < a + b {
+                    (0, 1)
+                } else if p < a + b + c {
+                    (1, 0)
+                } else {
+                    (1, 1)
+                };
+                src = (src << 1) | row;
+                dst = (dst << 1) | col;
+            }
+            if src != dst {
+                edges.push((src, dst));
+            }
+        }
+        Some(edges)
+    }
+}
+
+/// Evolving citation graph: every epoch publishes `papers_per_epoch` papers, each citing up to
+/// twice `citations_per_paper` earlier papers. A citation picks a random earlier paper with
+/// probability `random_citation`, and otherwise copies one of the references of that paper, which
+/// makes already cited papers more likely to be cited again.
+pub struct CitationModel {
+    rng: Rng,
+    papers_per_epoch: usize,
+    citations_per_paper: usize,
+    random_citation: f64,
+    references: Vec<Vec<usize>>,
+}
+
+impl CitationModel {
+    pub fn new(
+        papers_per_epoch: usize,
+        citations_per_paper: usize,
+        random_citation: f64,
+        seed: u64,
+    ) -> Self {
+        CitationModel {
+            rng: Rng::new(seed),
+            papers_per_epoch,
+            citations_per_paper,
+            random_citation,
+            references: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for CitationModel {
+    type Item = Vec<(usize, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // papers of this epoch only cite papers of earlier epochs, like the partitioned dataset
+        let published = self.references.len();
+
+        let mut edges = Vec::new();
+        for paper in published..published + self.papers_per_epoch {
+            let mut cited = Vec::new();
+            if published > 0 {
+                for _ in 0..self.rng.below(2 * self.citations_per_paper + 1) {
+                    let picked = self.rng.below(published);
+                    let references = &self.references[picked];
+                    let dst = if references.is_empty() || self.rng.unit() < self.random_citation {
+                        picked
+                    } else {
+                        references[self.rng.below(references.len())]
+                    };
+                    if !cited.contains(&dst) {
+                        cited.push(dst);
+                    }
+                }
+            }
+            edges.extend(cited.iter().map(|dst| (paper, *dst)));
+            self.references.push(cited);
+        }
+        Some(edges)
+    }
+}
@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+use abomonation_derive::Abomonation;
+use timely::dataflow::channels::pact::{Exchange, Pipeline};
+use timely::dataflow::{operators::*, Scope, Stream};
+
+use crate::convergence::{Residual, StopRule};
+use crate::dataflow::{self, Aggregate, WarmStart};
+use crate::telemetry::IterationStats;
+
+/// A citation as announced to one of the two vertices it links.
+#[derive(Abomonation, Clone, Copy, Debug)]
+enum Link {
+    /// the vertex cites the given vertex
+    Cites(usize),
+    /// the vertex is cited by the given vertex
+    CitedBy(usize),
+}
+
+/// Records entering the loop of the `HITS` operator at the start of an epoch.
+#[derive(Abomonation, Clone, Copy, Debug)]
+enum Change {
+    /// a link of a vertex for the worker owning it
+    Link(usize, Link),
+    /// the start of an epoch, which every worker takes part in, with or without new links
+    Epoch,
+}
+
+/// A score one vertex sends to another, to be summed up by the worker owning the receiving
+/// `vertex`.
+#[derive(Abomonation, Clone, Copy, Debug)]
+enum Message {
+    /// the hub score of a citing vertex, towards the authority score of the cited `vertex`
+    Hub(usize, f64),
+    /// the authority score of a cited vertex, towards the hub score of the citing `vertex`
+    Authority(usize, f64),
+    /// sent by worker `index` to itself every iteration, so that it takes part in the next one even
+    /// if no score arrives
+    Progress(usize),
+}
+
+impl Message {
+    fn owner(&self) -> usize {
+        match *self {
+            Message::Hub(vertex, _) | Message::Authority(vertex, _) => vertex,
+            Message::Progress(index) => index,
+        }
+    }
+}
+
+/// Records produced by the `HITS` operator in each iteration.
+#[derive(Clone, Debug)]
+enum Update {
+    Message(Message),
+    Progress(Progress),
+}
+
+/// How much the authority and hub scores held by one worker changed in this iteration, along with
+/// their sums, and how many messages it sent to other workers.
+#[derive(Abomonation, Clone, Copy, Debug, Default)]
+struct Progress {
+    authorities: Residual,
+    hubs: Residual,
+    exchanged: u64,
+}
+
+impl Aggregate for Progress {
+    fn merge(&mut self, other: &Self) {
+        self.authorities.merge(&other.authorities);
+        self.hubs.merge(&other.hubs);
+        self.exchanged += other.exchanged;
+    }
+
+    fn residual(&self) -> Residual {
+        let mut residual = self.authorities;
+        residual.merge(&self.hubs);
+        residual
+    }
+
+    fn exchanged(&self) -> u64 {
+        self.exchanged
+    }
+}
+
+/// The links and scores of a vertex, on the worker owning it.
+#[derive(Clone, Debug)]
+struct Scores {
+    cites: Vec<usize>,
+    cited_by: Vec<usize>,
+    authority: f64,
+    hub: f64,
+}
+
+/// A score of every vertex, such as its authority or its hub score.
+pub type ScoreStream<G> = Stream<G, (usize, f64)>;
+
+/// Calculates the HITS authority and hub scores of each epoch of `edges`, like `dataflow::pagerank`
+/// calculates ranks: a vertex is a good authority if good hubs cite it, and a good hub if it cites
+/// good authorities. Every iteration computes the authority of every vertex from the hub scores of
+/// the vertices citing it, and its hub score from the authorities it cites, both divided by their
+/// sum over all workers in the previous iteration, so that they neither vanish nor blow up.
+/// Returns the authority and the hub score of every vertex at the end of every epoch, on the worker
+/// owning the vertex, and the `IterationStats` of every iteration, whose residual covers both.
+///
+/// Since every iteration divides by the sum of the previous one, the residual is best measured with
+/// `Norm::Relative`. `WarmStart::Mean` and `WarmStart::Teleport` start new vertices at 1.0, like
+/// `WarmStart::Previous`.
+pub fn hits<G>(
+    edges: &Stream<G, (usize, usize)>,
+    stop_rule: StopRule,
+    warm_start: WarmStart,
+) -> (ScoreStream<G>, ScoreStream<G>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    // the authority of a vertex comes from the vertices citing it, so unlike the ranks of
+    // `pagerank`, every vertex needs to know its citations in both directions
+    let links = edges.flat_map(|(src, dst)| {
+        [
+            Change::Link(src, Link::Cites(dst)),
+            Change::Link(dst, Link::CitedBy(src)),
+        ]
+    });
+
+    // every worker takes part in every epoch, even without any new link of its own, as the sums
+    // of its scores scale the first iteration
+    let epochs = edges
+        .unary(Pipeline, "Epochs", |_capability, _info| {
+            let mut edge_vec = Vec::new();
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut edge_vec);
+                    edge_vec.clear();
+                    output.session(&time).give(Change::Epoch);
+                });
+            }
+        })
+        .broadcast();
+
+    let mut scope = edges.scope();
+    let (messages, stats) = scope.iterative::<u64, _, _>(|subscope| {
+        let (handle, message_stream) = subscope.loop_variable(1);
+        let (index, peers) = (subscope.index(), subscope.peers());
+
+        let updates = links.concat(&epochs).enter(subscope).binary_frontier(
+            &message_stream,
+            Exchange::new(move |change: &Change| match change {
+                Change::Link(vert, _) => *vert as u64,
+                Change::Epoch => index as u64,
+            }),
+            Exchange::new(|message: &Message| message.owner() as u64),
+            "HITS",
+            move |_capability, _info| {
+                let mut scores: HashMap<usize, Scores> = HashMap::new();
+
+                // where we stash out-of-order data
+                let mut link_stash = HashMap::new();
+                let mut message_stash = HashMap::new();
+
+                // empty list for swapping
+                let mut link_vec = Vec::new();
+                let mut message_vec = Vec::new();
+
+                move |input1, input2, output| {
+                    input1.for_each(|time, data| {
+                        data.swap(&mut link_vec);
+                        link_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut link_vec);
+                    });
+
+                    input2.for_each(|time, data| {
+                        data.swap(&mut message_vec);
+                        message_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut message_vec);
+                    });
+
+                    let frontiers = &[input1.frontier(), input2.frontier()];
+
+                    for (time, new_links) in link_stash.iter_mut() {
+                        // scores are shared by all epochs, wait until earlier epochs stop iterating
+                        let earlier_epochs_done = input2
+                            .frontier()
+                            .frontier()
+                            .iter()
+                            .all(|t| t.outer >= time.outer);
+
+                        if frontiers.iter().all(|f| !f.less_equal(time)) && earlier_epochs_done {
+                            if warm_start == WarmStart::Cold {
+                                for vertex in scores.values_mut() {
+                                    vertex.authority = 1.0;
+                                    vertex.hub = 1.0;
+                                }
+                            }
+
+                            for change in new_links.drain(..) {
+                                let (vert, link) = match change {
+                                    Change::Link(vert, link) => (vert, link),
+                                    Change::Epoch => continue,
+                                };
+                                let vertex = scores.entry(vert).or_insert(Scores {
+                                    cites: Vec::new(),
+                                    cited_by: Vec::new(),
+                                    authority: 1.0,
+                                    hub: 1.0,
+                                });
+                                match link {
+                                    Link::Cites(dst) => vertex.cites.push(dst),
+                                    Link::CitedBy(src) => vertex.cited_by.push(src),
+                                }
+                            }
+
+                            // nothing to compare with yet, but the sums scale the first messages
+                            let mut progress = Progress::default();
+                            for vertex in scores.values() {
+                                progress.authorities.add(vertex.authority, vertex.authority);
+                                progress.hubs.add(vertex.hub, vertex.hub);
+                            }
+                            let messages = score_messages(&scores, index, peers, &mut progress);
+                            let mut session = output.session(time);
+                            for message in messages {
+                                session.give(Update::Message(message));
+                            }
+                            if !scores.is_empty() {
+                                session.give(Update::Message(Message::Progress(index)));
+                            }
+                            session.give(Update::Progress(progress));
+                        }
+                    }
+                    link_stash.retain(|_key, val| !val.is_empty());
+
+                    for (time, messages) in message_stash.iter_mut() {
+                        if frontiers.iter().all(|f| !f.less_equal(time)) {
+                            let mut hub_sum = HashMap::new();
+                            let mut authority_sum = HashMap::new();
+                            for message in messages.drain(..) {
+                                match message {
+                                    Message::Hub(vert, hub) => {
+                                        *hub_sum.entry(vert).or_insert(0.0f64) += hub
+                                    }
+                                    Message::Authority(vert, authority) => {
+                                        *authority_sum.entry(vert).or_insert(0.0f64) += authority
+                                    }
+                                    Message::Progress(_) => {}
+                                }
+                            }
+
+                            // the authority of a vertex sums up the hubs citing it, and the other
+                            // way around
+                            let mut progress = Progress::default();
+                            for (vert, vertex) in scores.iter_mut() {
+                                let authority = *hub_sum.get(vert).unwrap_or(&0.0f64);
+                                let hub = *authority_sum.get(vert).unwrap_or(&0.0f64);
+                                progress.authorities.add(authority, vertex.authority);
+                                progress.hubs.add(hub, vertex.hub);
+                                vertex.authority = authority;
+                                vertex.hub = hub;
+                            }
+
+                            let messages = score_messages(&scores, index, peers, &mut progress);
+                            let mut session = output.session(time);
+                            for message in messages {
+                                session.give(Update::Message(message));
+                            }
+                            session.give(Update::Message(Message::Progress(index)));
+                            session.give(Update::Progress(progress));
+                        }
+                    }
+                    message_stash.retain(|_key, val| !val.is_empty());
+                }
+            },
+        );
+
+        let messages = updates.flat_map(|update| match update {
+            Update::Message(message) => Some(message),
+            Update::Progress(..) => None,
+        });
+        let local_progress = updates.flat_map(|update| match update {
+            Update::Progress(progress) => Some(progress),
+            Update::Message(..) => None,
+        });
+
+        let global_progress = dataflow::all_reduce(&local_progress);
+        let stats = dataflow::telemetry(&global_progress, index);
+
+        // divide the scores by their sum before they are summed up again, where there is one
+        let (continuing, leaving) = dataflow::converge(
+            &messages,
+            &global_progress,
+            stop_rule,
+            |progress: &Progress, message| match message {
+                Message::Hub(vert, hub) if progress.hubs.rank_sum > 0.0 => {
+                    Message::Hub(vert, hub / progress.hubs.rank_sum)
+                }
+                Message::Authority(vert, authority) if progress.authorities.rank_sum > 0.0 => {
+                    Message::Authority(vert, authority / progress.authorities.rank_sum)
+                }
+                message => message,
+            },
+        );
+
+        continuing.connect_loop(handle);
+
+        // break out the iteration because the stop rule holds
+        let leaving = leaving.flat_map(|message| match message {
+            Message::Progress(_) => None,
+            message => Some(message),
+        });
+        (leaving.leave(), stats.leave())
+    });
+
+    let scores = final_scores(&messages);
+    let authorities = scores.map(|(vert, authority, _)| (vert, authority));
+    let hubs = scores.map(|(vert, _, hub)| (vert, hub));
+    (authorities, hubs, stats)
+}
+
+/// The hub score of every vertex for the vertices it cites, and its authority for the vertices
+/// citing it, counting the messages to other workers in `progress`.
+fn score_messages(
+    scores: &HashMap<usize, Scores>,
+    index: usize,
+    peers: usize,
+    progress: &mut Progress,
+) -> Vec<Message> {
+    let mut messages = Vec::new();
+    for vertex in scores.values() {
+        for dst in &vertex.cites {
+            progress.exchanged += u64::from(*dst % peers != index);
+            messages.push(Message::Hub(*dst, vertex.hub));
+        }
+        for src in &vertex.cited_by {
+            progress.exchanged += u64::from(*src % peers != index);
+            messages.push(Message::Authority(*src, vertex.authority));
+        }
+    }
+    messages
+}
+
+/// Once an epoch left the iterative scope, calculates the final authority and hub score of each
+/// vertex from the `messages` of the last iteration, on the worker owning the vertex.
+fn final_scores<G>(messages: &Stream<G, Message>) -> Stream<G, (usize, f64, f64)>
+where
+    G: Scope<Timestamp = u64>,
+{
+    messages.unary_frontier(
+        Exchange::new(|x: &Message| x.owner() as u64),
+        "Scores",
+        |_capability, _info| {
+            let mut score_stash = HashMap::new();
+            let mut message_vec = Vec::new();
+
+            move |input, output| {
+                // sum up messages as they arrive
+                input.for_each(|time, data| {
+                    data.swap(&mut message_vec);
+                    let score_sum = score_stash.entry(time.retain()).or_insert(HashMap::new());
+                    for message in message_vec.drain(..) {
+                        let (authority, hub) =
+                            score_sum.entry(message.owner()).or_insert((0.0f64, 0.0f64));
+                        match message {
+                            Message::Hub(_, score) => *authority += score,
+                            Message::Authority(_, score) => *hub += score,
+                            Message::Progress(_) => {}
+                        }
+                    }
+                });
+
+                for (time, score_sum) in score_stash.iter_mut() {
+                    if !input.frontier().less_equal(time) {
+                        let mut session = output.session(time);
+                        for (vert, (authority, hub)) in score_sum.drain() {
+                            session.give((vert, authority, hub));
+                        }
+                    }
+                }
+                score_stash.retain(|_key, val| !val.is_empty());
+            }
+        },
+    )
+}
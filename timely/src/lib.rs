@@ -1 +1,19 @@
-pub mod utils;
\ No newline at end of file
+// `abomonation_derive` puts the impls it derives inside a constant
+#![allow(non_local_definitions)]
+
+pub mod blocks;
+pub mod checkpoint;
+pub mod cheirank;
+pub mod cli;
+pub mod cluster;
+pub mod config;
+pub mod convergence;
+pub mod dataflow;
+pub mod generators;
+pub mod hits;
+pub mod montecarlo;
+pub mod reference;
+pub mod server;
+pub mod telemetry;
+pub mod trustrank;
+pub mod utils;
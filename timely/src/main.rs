@@ -0,0 +1,231 @@
+use std::cell::RefCell;
+use std::net::TcpListener;
+use std::rc::Rc;
+use std::thread;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+
+use pagerank::cheirank;
+use pagerank::cli::{Args, Error, Measure, Source};
+use pagerank::cluster;
+use pagerank::dataflow::{self, State};
+use pagerank::generators;
+use pagerank::hits;
+use pagerank::montecarlo;
+use pagerank::server::{self, RankStore};
+use pagerank::telemetry::Summary;
+use pagerank::trustrank;
+use pagerank::utils;
+
+fn main() {
+    let args = match Args::parse(std::env::args()) {
+        Ok(args) => args,
+        Err(Error::Help) => {
+            println!("{}", Error::Help);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // this process only starts the others, which run as it was told to plus their place among them
+    if let Some(processes) = args.launch {
+        args.config.print(args.format);
+        let program = std::env::current_exe().expect("cannot find the pagerank binary");
+        let hostfile = std::env::temp_dir().join(format!("pagerank-{}.hosts", std::process::id()));
+        let program_args = std::env::args().skip(1).collect::<Vec<_>>();
+        match cluster::launch(&program, &program_args, processes, &hostfile) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("error: cannot start the processes: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // process 0 holds worker 0, which gathers the ranks, so it answers the queries about them
+    let store = RankStore::new();
+    let query_server = match &args.serve {
+        Some(addr) if args.config.topology.process.unwrap_or(0) == 0 => {
+            let listener = match TcpListener::bind(addr) {
+                Ok(listener) => listener,
+                Err(err) => {
+                    eprintln!("error: cannot listen on {}: {}", addr, err);
+                    std::process::exit(1);
+                }
+            };
+            eprintln!(
+                "answering rank queries on {}",
+                listener.local_addr().unwrap()
+            );
+            let store = store.clone();
+            Some(thread::spawn(move || server::serve(listener, store)))
+        }
+        _ => None,
+    };
+
+    let timely_args = args.timely_args.clone();
+    let result = timely::execute_from_args(timely_args.into_iter(), move |worker| {
+        // input is a stream of edges in the format of (from, to)
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let summary = Rc::new(RefCell::new(Summary::new()));
+
+        // the graph and ranks of this worker, as saved after the epoch the run continues from
+        let state = match (&args.checkpoint, args.restored) {
+            (Some(checkpoint), Some(epoch)) => checkpoint
+                .load(epoch, worker.index(), worker.peers())
+                .expect("failed to restore the checkpoint"),
+            _ => State::default(),
+        };
+        let state = Rc::new(RefCell::new(state));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (single_year, stats) = match args.measure {
+                Measure::PageRank | Measure::Katz | Measure::Eigenvector | Measure::CiteRank => {
+                    dataflow::pagerank_from(&edge_stream, args.params, state.clone())
+                }
+                Measure::CheiRank => {
+                    cheirank::cheirank_from(&edge_stream, args.params, state.clone())
+                }
+                Measure::TwoDimensional => {
+                    let (ranks, stats) = dataflow::pagerank(&edge_stream, args.params);
+                    let (cheiranks, _stats) = cheirank::cheirank(&edge_stream, args.params);
+                    cheirank::write_top_2d(&ranks, &cheiranks, args.top_k, args.format)
+                        .probe_with(&mut probe);
+                    (ranks, stats)
+                }
+                Measure::Hits => {
+                    let (authorities, hubs, stats) =
+                        hits::hits(&edge_stream, args.params.stop_rule, args.params.warm_start);
+                    dataflow::write_top_scores(&hubs, args.top_k, args.format, "hub")
+                        .probe_with(&mut probe);
+                    (authorities, stats)
+                }
+                Measure::TrustRank => {
+                    let (ranks, _stats) = dataflow::pagerank(&edge_stream, args.params);
+                    let (trustranks, stats) =
+                        trustrank::trustrank(&edge_stream, args.params, args.teleport.clone());
+                    trustrank::write_anomalies(
+                        &ranks,
+                        &trustranks,
+                        args.anomaly_ratio,
+                        args.top_k,
+                        args.format,
+                    )
+                    .probe_with(&mut probe);
+                    (trustranks, stats)
+                }
+                Measure::AntiTrustRank => trustrank::anti_trustrank_from(
+                    &edge_stream,
+                    args.params,
+                    args.teleport.clone(),
+                    state.clone(),
+                ),
+                Measure::MonteCarlo => {
+                    let (estimates, stats) = montecarlo::montecarlo(
+                        &edge_stream,
+                        args.walks,
+                        args.params.damping,
+                        args.seed,
+                    );
+                    montecarlo::write_top_estimates(&estimates, args.top_k, args.format)
+                        .probe_with(&mut probe);
+                    (
+                        estimates.map(|(vert, estimate)| (vert, estimate.rank)),
+                        stats,
+                    )
+                }
+            };
+
+            // convergence of each iteration, only reported by worker 0
+            let summary = summary.clone();
+            stats.inspect_batch(move |time, data| {
+                for stats in data {
+                    summary.borrow_mut().record(*time, *stats);
+                }
+            });
+
+            // estimates were written along with their bounds, and 2d ranks in their own order
+            if !matches!(args.measure, Measure::MonteCarlo | Measure::TwoDimensional) {
+                let measure = args.measure.name();
+                dataflow::write_top_scores(&single_year, args.top_k, args.format, measure)
+                    .probe_with(&mut probe);
+            }
+            if args.serve.is_some() {
+                server::publish_ranks(&single_year, store.clone()).probe_with(&mut probe);
+            }
+        });
+
+        // the first worker of all processes prints, unless the process starting them did
+        if worker.index() == 0 && !args.launched {
+            args.config.print(args.format);
+        }
+
+        // every worker reads or generates its own share of the edges, in every process
+        let (index, peers) = (worker.index(), worker.peers());
+        let batches: Box<dyn Iterator<Item = Vec<(usize, usize)>>> = match &args.source {
+            Source::Files(data_dir) => {
+                let data_dir = data_dir.clone();
+                Box::new((args.first..=args.end).map(move |epoch| {
+                    utils::get_citation_shard_from_dir(&data_dir, epoch, index, peers)
+                        .unwrap_or_else(|err| {
+                            eprintln!("error: {}", err);
+                            std::process::exit(1);
+                        })
+                }))
+            }
+            Source::Synthetic { model, scale, seed } => Box::new(
+                generators::generator(*model, *scale, *seed, index, peers)
+                    .skip(args.first.saturating_sub(args.origin) as usize),
+            ),
+        };
+
+        // feeding edges, those before the start epoch as a single snapshot
+        input.advance_to(args.start);
+        let epochs = utils::start_at(batches, args.first, args.start);
+        for (epoch, edges) in epochs.take((args.end - args.start + 1) as usize) {
+            for e in edges {
+                input.send(e);
+            }
+            input.advance_to(epoch + 1);
+
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // the ranks of the epoch are out, so the state is final until the next edges arrive
+            if let Some(checkpoint) = &args.checkpoint {
+                checkpoint
+                    .save(epoch, worker.index(), worker.peers(), &state.borrow())
+                    .expect("failed to save the checkpoint");
+            }
+        }
+
+        if worker.index() == 0 && args.summary {
+            summary.borrow().print(args.params.stop_rule.norm);
+        }
+    });
+
+    // timely rejects the arguments it does not know
+    let guards = match result {
+        Ok(guards) => guards,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, pagerank::cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    drop(guards);
+
+    // the ranks of the last epoch stay available until the process is stopped
+    if let Some(query_server) = query_server {
+        eprintln!("all epochs are done, still answering rank queries");
+        if let Err(err) = query_server.join().unwrap() {
+            eprintln!("error: the query server stopped: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
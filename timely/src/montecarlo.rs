@@ -0,0 +1,590 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use abomonation_derive::Abomonation;
+use serde_json::json;
+use timely::dataflow::channels::pact::{Exchange, Pipeline};
+use timely::dataflow::operators::generic::OutputHandle;
+use timely::dataflow::{operators::*, Scope, Stream};
+
+use crate::convergence::Residual;
+use crate::dataflow::{self, Format};
+use crate::generators::Rng;
+use crate::telemetry::IterationStats;
+
+/// How many standard deviations the bounds of an `Estimate` are away from it, for a confidence of
+/// 95%.
+pub const Z_95: f64 = 1.96;
+
+/// One of the random walks started from every vertex.
+#[derive(Abomonation, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Walk {
+    origin: usize,
+    index: usize,
+}
+
+/// What a walk did once it arrived at a vertex, as recorded by the worker owning the vertex.
+#[derive(Clone, Copy, Debug)]
+enum Visit {
+    /// the walk stopped here, as a random surfer jumps elsewhere
+    Stopped,
+    /// the walk went on to the given vertex
+    Moved(usize),
+    /// the walk went on, but the vertex cites nothing yet, so it waits for the vertex to cite
+    /// something
+    Stuck,
+}
+
+/// A step of a walk, handled by the worker owning the vertex it names, but for `Propose`.
+#[derive(Abomonation, Clone, Copy, Debug)]
+enum Message {
+    /// the walk arrives at a vertex after a number of steps
+    Arrive(Walk, u64, usize),
+    /// the visit of a walk after a number of steps to a vertex is no longer part of the walk,
+    /// neither are any of its later ones
+    Erase(Walk, u64, usize),
+    /// the walk may leave a vertex after a number of steps for another vertex instead, handled by
+    /// the worker owning the origin of the walk, which only keeps the earliest of them
+    Propose(Walk, u64, usize, usize),
+    /// the walk leaves a vertex after a number of steps for another vertex instead
+    Reroute(Walk, u64, usize, usize),
+}
+
+impl Message {
+    /// The vertex whose owner handles the message.
+    fn owner(&self) -> usize {
+        match *self {
+            Message::Arrive(_, _, vertex)
+            | Message::Erase(_, _, vertex)
+            | Message::Reroute(_, _, vertex, _) => vertex,
+            Message::Propose(walk, _, _, _) => walk.origin,
+        }
+    }
+}
+
+/// Records produced by the `MonteCarlo` operator in each iteration.
+#[derive(Clone, Debug)]
+enum Update {
+    Message(Message),
+    /// how many visits a vertex gained or lost
+    Visits(usize, i64),
+    /// how much the estimates held by one worker changed in this iteration, and how many messages
+    /// it sent to other workers
+    Residual(Residual, u64),
+}
+
+/// The PageRank of a vertex as estimated from the walks visiting it, along with the bounds it lies
+/// within with a confidence of 95%.
+#[derive(Abomonation, Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub rank: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Estimate {
+    /// The estimate out of the number of `visits` to a vertex, of which the first visits of its
+    /// own `walks` are certain, and the others roughly follow a Poisson distribution.
+    pub fn from_visits(visits: u64, walks: usize, damping: f64) -> Self {
+        let scale = (1.0 - damping) / walks as f64;
+        let deviation = (visits.saturating_sub(walks as u64) as f64).sqrt();
+        Estimate {
+            rank: scale * visits as f64,
+            lower: scale * (visits as f64 - Z_95 * deviation).max(0.0),
+            upper: scale * (visits as f64 + Z_95 * deviation),
+        }
+    }
+}
+
+/// Estimates the PageRank of each epoch of `edges` with `walks` random walks started from every
+/// vertex, each of which stops with probability `1 - damping` at every vertex it arrives at, and
+/// otherwise follows a random citation, or waits where there is none. The rank of a vertex is
+/// `1 - damping` times the number of visits to it per walk, the same ranks `dataflow::pagerank`
+/// converges to, and closer to them the more walks there are.
+///
+/// Walks move on to the worker owning the vertex they arrive at, which records the visit, and
+/// are kept from one epoch to the next. A new citation of a vertex takes over each walk leaving
+/// it with the probability a new walk would take it, so that only the rest of these walks is
+/// walked again, along with the walks of new vertices. Every step draws from a generator seeded
+/// by `seed`, the walk, the step and the epoch, so that the estimates do not depend on the number
+/// of workers. Returns the estimate of every vertex at the end of every epoch, on the worker
+/// owning the vertex, and the `IterationStats` of every iteration, where walks take one step, and
+/// whose residual is how much the estimates changed.
+pub fn montecarlo<G>(
+    edges: &Stream<G, (usize, usize)>,
+    walks: usize,
+    damping: f64,
+    seed: u64,
+) -> (Stream<G, (usize, Estimate)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    let vertices = edges.flat_map(|(src, dst)| [(src, Some(dst)), (dst, None)]);
+    // what a single visit adds to the estimate of a vertex
+    let weight = (1.0 - damping) / walks as f64;
+
+    let mut scope = edges.scope();
+    let (visits, stats) = scope.iterative::<u64, _, _>(|subscope| {
+        let (handle, message_stream) = subscope.loop_variable(1);
+        let (index, peers) = (subscope.index(), subscope.peers());
+
+        let updates = vertices.enter(subscope).binary_frontier(
+            &message_stream,
+            Exchange::new(|x: &(usize, Option<usize>)| x.0 as u64),
+            Exchange::new(|x: &Message| x.owner() as u64),
+            "MonteCarlo",
+            move |_capability, _info| {
+                // the cited vertices and the visits of every vertex this worker owns
+                let mut edges: HashMap<usize, Vec<usize>> = HashMap::new();
+                let mut visits: HashMap<usize, HashMap<(Walk, u64), Visit>> = HashMap::new();
+
+                // where we stash out-of-order data
+                let mut edge_stash = HashMap::new();
+                let mut message_stash = HashMap::new();
+
+                // empty list for swapping
+                let mut edge_vec = Vec::new();
+                let mut message_vec = Vec::new();
+
+                move |input1, input2, output| {
+                    input1.for_each(|time, data| {
+                        data.swap(&mut edge_vec);
+                        edge_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut edge_vec);
+                    });
+
+                    input2.for_each(|time, data| {
+                        data.swap(&mut message_vec);
+                        message_stash
+                            .entry(time.retain())
+                            .or_insert(Vec::new())
+                            .append(&mut message_vec);
+                    });
+
+                    let frontiers = &[input1.frontier(), input2.frontier()];
+
+                    for (time, edge_changes) in edge_stash.iter_mut() {
+                        // walks are shared by all epochs, wait until earlier epochs are walked
+                        let earlier_epochs_done = input2
+                            .frontier()
+                            .frontier()
+                            .iter()
+                            .all(|t| t.outer >= time.outer);
+
+                        if frontiers.iter().all(|f| !f.less_equal(time)) && earlier_epochs_done {
+                            let epoch = time.outer;
+                            let mut steps = Steps::new(weight, index, peers);
+
+                            let mut new_vertices = Vec::new();
+                            let mut new_citations = HashMap::new();
+                            for (vert, dst) in edge_changes.drain(..) {
+                                if let Entry::Vacant(entry) = visits.entry(vert) {
+                                    entry.insert(HashMap::new());
+                                    new_vertices.push(vert);
+                                }
+                                if let Some(dst) = dst {
+                                    edges.entry(vert).or_default().push(dst);
+                                    *new_citations.entry(vert).or_insert(0) += 1;
+                                }
+                            }
+
+                            // walks that left a vertex before take one of its new citations
+                            // instead as often as a new walk would, and waiting walks take any
+                            for (vert, new) in new_citations {
+                                // in the same order however the edges were spread over workers
+                                let dsts = edges.get_mut(&vert).unwrap();
+                                let len = dsts.len();
+                                dsts[len - new..].sort_unstable();
+
+                                let dsts = &edges[&vert];
+                                let share = new as f64 / dsts.len() as f64;
+                                for (&(walk, step), visit) in &visits[&vert] {
+                                    let mut rng = rng(seed, walk, step, epoch, REROUTE);
+                                    let next = match visit {
+                                        Visit::Moved(_) if rng.unit() < share => {
+                                            dsts[dsts.len() - new + rng.below(new)]
+                                        }
+                                        Visit::Stuck => dsts[rng.below(dsts.len())],
+                                        Visit::Stopped | Visit::Moved(_) => continue,
+                                    };
+                                    steps.send(Message::Propose(walk, step, vert, next));
+                                }
+                            }
+
+                            // new vertices start their own walks, with the new citations in place
+                            for vert in new_vertices {
+                                for walk_index in 0..walks {
+                                    let walk = Walk {
+                                        origin: vert,
+                                        index: walk_index,
+                                    };
+                                    let visit = arrive(&edges, seed, damping, walk, 0, vert, epoch);
+                                    steps.record(walk, 0, vert, visit, &mut visits);
+                                }
+                            }
+
+                            let mut session = output.session(time);
+                            for update in steps.finish() {
+                                session.give(update);
+                            }
+                        }
+                    }
+                    edge_stash.retain(|_key, val| !val.is_empty());
+
+                    for (time, messages) in message_stash.iter_mut() {
+                        if frontiers.iter().all(|f| !f.less_equal(time)) {
+                            let epoch = time.outer;
+                            let mut steps = Steps::new(weight, index, peers);
+
+                            // the rest of a walk is erased before the new rest arrives, which may
+                            // visit the same vertices after the same number of steps
+                            messages.sort_by_key(|message| match message {
+                                Message::Erase(..) => 0,
+                                Message::Reroute(..) => 1,
+                                Message::Arrive(..) => 2,
+                                Message::Propose(..) => 3,
+                            });
+
+                            let mut earliest = HashMap::new();
+                            for message in messages.drain(..) {
+                                match message {
+                                    Message::Erase(walk, step, vert) => {
+                                        steps.erase(walk, step, vert, &mut visits)
+                                    }
+                                    Message::Reroute(walk, step, vert, next) => {
+                                        let visit = visits
+                                            .get_mut(&vert)
+                                            .and_then(|visits| visits.get_mut(&(walk, step)))
+                                            .expect("rerouted walk never visited the vertex");
+                                        if let Visit::Moved(old) = *visit {
+                                            steps.send(Message::Erase(walk, step + 1, old));
+                                        }
+                                        *visit = Visit::Moved(next);
+                                        steps.send(Message::Arrive(walk, step + 1, next));
+                                    }
+                                    Message::Arrive(walk, step, vert) => {
+                                        let visit =
+                                            arrive(&edges, seed, damping, walk, step, vert, epoch);
+                                        steps.record(walk, step, vert, visit, &mut visits);
+                                    }
+                                    Message::Propose(walk, step, vert, next) => {
+                                        // the rest of the walk after the earliest one is gone
+                                        let proposal =
+                                            earliest.entry(walk).or_insert((step, vert, next));
+                                        if step < proposal.0 {
+                                            *proposal = (step, vert, next);
+                                        }
+                                    }
+                                }
+                            }
+                            for (walk, (step, vert, next)) in earliest {
+                                steps.send(Message::Reroute(walk, step, vert, next));
+                            }
+
+                            let mut session = output.session(time);
+                            for update in steps.finish() {
+                                session.give(update);
+                            }
+                        }
+                    }
+                    message_stash.retain(|_key, val| !val.is_empty());
+                }
+            },
+        );
+
+        let messages = updates.flat_map(|update| match update {
+            Update::Message(message) => Some(message),
+            Update::Visits(..) | Update::Residual(..) => None,
+        });
+        let visits = updates.flat_map(|update| match update {
+            Update::Visits(vert, visits) => Some((vert, visits)),
+            Update::Message(..) | Update::Residual(..) => None,
+        });
+        let local_residual = updates.flat_map(|update| match update {
+            Update::Residual(residual, exchanged) => Some((residual, exchanged)),
+            Update::Message(..) | Update::Visits(..) => None,
+        });
+
+        let global_residual = dataflow::all_reduce(&local_residual);
+        let stats = dataflow::telemetry(&global_residual, index);
+
+        // walks go on until all of them stopped
+        messages.connect_loop(handle);
+        (visits.leave(), stats.leave())
+    });
+
+    // every worker takes part in every epoch, even without any vertex whose visits changed
+    let epochs = edges
+        .unary(Pipeline, "Epochs", |_capability, _info| {
+            let mut edge_vec = Vec::new();
+            move |input, output| {
+                input.for_each(|time, data| {
+                    data.swap(&mut edge_vec);
+                    edge_vec.clear();
+                    output.session(&time).give(());
+                });
+            }
+        })
+        .broadcast();
+
+    (final_estimates(&visits, &epochs, walks, damping), stats)
+}
+
+/// Salts of the generators of a step, which decide where a walk arriving at a vertex goes, and
+/// whether a walk leaving a vertex takes one of its new citations instead.
+const ARRIVE: u64 = 0x5851_f42d_4c95_7f2d;
+const REROUTE: u64 = 0x1405_7b7e_f767_814f;
+
+/// The generator of a step of `walk`, the same whichever worker takes the step.
+fn rng(seed: u64, walk: Walk, step: u64, epoch: u64, salt: u64) -> Rng {
+    let mut rng = Rng::new(seed ^ salt);
+    let mut state = rng.next_u64();
+    for value in [walk.origin as u64, walk.index as u64, step, epoch] {
+        state = Rng::new(state ^ value).next_u64();
+    }
+    Rng::new(state)
+}
+
+/// Where `walk` goes once it arrived at `vert` after `step` steps.
+fn arrive(
+    edges: &HashMap<usize, Vec<usize>>,
+    seed: u64,
+    damping: f64,
+    walk: Walk,
+    step: u64,
+    vert: usize,
+    epoch: u64,
+) -> Visit {
+    let mut rng = rng(seed, walk, step, epoch, ARRIVE);
+    if rng.unit() >= damping {
+        return Visit::Stopped;
+    }
+    match edges.get(&vert) {
+        Some(dsts) if !dsts.is_empty() => Visit::Moved(dsts[rng.below(dsts.len())]),
+        _ => Visit::Stuck,
+    }
+}
+
+/// What one worker sends out in an iteration, and how much its estimates changed.
+struct Steps {
+    weight: f64,
+    index: usize,
+    peers: usize,
+    updates: Vec<Update>,
+    residual: Residual,
+    exchanged: u64,
+}
+
+impl Steps {
+    fn new(weight: f64, index: usize, peers: usize) -> Self {
+        Steps {
+            weight,
+            index,
+            peers,
+            updates: Vec::new(),
+            residual: Residual::default(),
+            exchanged: 0,
+        }
+    }
+
+    fn send(&mut self, message: Message) {
+        self.exchanged += u64::from(message.owner() % self.peers != self.index);
+        self.updates.push(Update::Message(message));
+    }
+
+    /// Records the `visit` of `walk` to `vert` after `step` steps, and sends the walk on.
+    fn record(
+        &mut self,
+        walk: Walk,
+        step: u64,
+        vert: usize,
+        visit: Visit,
+        visits: &mut HashMap<usize, HashMap<(Walk, u64), Visit>>,
+    ) {
+        visits.entry(vert).or_default().insert((walk, step), visit);
+        self.residual.add(self.weight, 0.0);
+        self.updates.push(Update::Visits(vert, 1));
+        if let Visit::Moved(next) = visit {
+            self.send(Message::Arrive(walk, step + 1, next));
+        }
+    }
+
+    /// Erases the visit of `walk` to `vert` after `step` steps, and the rest of the walk.
+    fn erase(
+        &mut self,
+        walk: Walk,
+        step: u64,
+        vert: usize,
+        visits: &mut HashMap<usize, HashMap<(Walk, u64), Visit>>,
+    ) {
+        let visit = visits
+            .get_mut(&vert)
+            .and_then(|visits| visits.remove(&(walk, step)))
+            .expect("erased walk never visited the vertex");
+        self.residual.add(0.0, self.weight);
+        self.updates.push(Update::Visits(vert, -1));
+        if let Visit::Moved(next) = visit {
+            self.send(Message::Erase(walk, step + 1, next));
+        }
+    }
+
+    /// The messages and visits of the iteration, followed by its residual.
+    fn finish(mut self) -> Vec<Update> {
+        self.updates
+            .push(Update::Residual(self.residual, self.exchanged));
+        self.updates
+    }
+}
+
+/// Once an epoch is walked, sums up the `visits` each vertex gained or lost in it, and estimates the
+/// rank of every vertex on the worker owning it. `epochs` tells every worker which epochs there
+/// are.
+fn final_estimates<G>(
+    visits: &Stream<G, (usize, i64)>,
+    epochs: &Stream<G, ()>,
+    walks: usize,
+    damping: f64,
+) -> Stream<G, (usize, Estimate)>
+where
+    G: Scope<Timestamp = u64>,
+{
+    visits.binary_frontier(
+        epochs,
+        Exchange::new(|x: &(usize, i64)| x.0 as u64),
+        Pipeline,
+        "Estimates",
+        move |_capability, _info| {
+            let mut counts: HashMap<usize, i64> = HashMap::new();
+            let mut visit_stash = HashMap::new();
+            let mut visit_vec = Vec::new();
+            let mut epoch_vec = Vec::new();
+
+            move |input1, input2, output| {
+                input1.for_each(|time, data| {
+                    data.swap(&mut visit_vec);
+                    visit_stash
+                        .entry(time.retain())
+                        .or_insert(Vec::new())
+                        .append(&mut visit_vec);
+                });
+                input2.for_each(|time, data| {
+                    data.swap(&mut epoch_vec);
+                    epoch_vec.clear();
+                    visit_stash.entry(time.retain()).or_insert(Vec::new());
+                });
+
+                // epochs are estimated in order, each one on top of the counts of the earlier ones
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                let mut done = visit_stash
+                    .keys()
+                    .filter(|time| frontiers.iter().all(|f| !f.less_equal(time.time())))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                done.sort_by_key(|time| *time.time());
+                for time in done {
+                    for (vert, visits) in visit_stash.remove(&time).unwrap() {
+                        *counts.entry(vert).or_insert(0) += visits;
+                    }
+                    let mut session = output.session(&time);
+                    for (vert, count) in counts.iter() {
+                        let estimate = Estimate::from_visits(*count as u64, walks, damping);
+                        session.give((*vert, estimate));
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// Gathers the `estimates` of each epoch on worker 0, and prints the top `k` of them with their
+/// bounds, all normalized so that the ranks sum up to the number of vertices, in the given
+/// `format`.
+pub fn write_top_estimates<G>(
+    estimates: &Stream<G, (usize, Estimate)>,
+    k: usize,
+    format: Format,
+) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    estimates.unary_frontier(Exchange::new(|_| 0), "Result", move |_capability, _info| {
+        let mut estimate_stash = HashMap::new();
+        let mut estimate_vec = Vec::new();
+        let mut header_printed = false;
+
+        move |input, _output: &mut OutputHandle<u64, (), _>| {
+            // hold on to final estimates until it is time
+            input.for_each(|time, data| {
+                data.swap(&mut estimate_vec);
+                estimate_stash
+                    .entry(time.retain())
+                    .or_insert(Vec::new())
+                    .append(&mut estimate_vec);
+            });
+
+            for (time, estimates) in estimate_stash.iter_mut() {
+                if !input.frontier.less_equal(time) {
+                    // get normalize factor
+                    let sum: f64 = estimates.iter().map(|(_, estimate)| estimate.rank).sum();
+                    let normalize_factor = estimates.len() as f64 / sum;
+
+                    // get top estimates
+                    estimates.sort_by(|(_, a), (_, b)| b.rank.partial_cmp(&a.rank).unwrap());
+                    let top = estimates.iter().take(k).map(|(vert, estimate)| {
+                        let estimate = Estimate {
+                            rank: estimate.rank * normalize_factor,
+                            lower: estimate.lower * normalize_factor,
+                            upper: estimate.upper * normalize_factor,
+                        };
+                        (*vert, estimate)
+                    });
+                    match format {
+                        Format::Text => {
+                            println!("--- year {:?} top {} ---", time.time(), k);
+                            for (vert, e) in top {
+                                println!(
+                                    "{:?} has rank score: {:?}, between {:?} and {:?}",
+                                    vert, e.rank, e.lower, e.upper
+                                );
+                            }
+                        }
+                        Format::Csv => {
+                            if !header_printed {
+                                println!("epoch,position,vertex,rank,lower,upper");
+                                header_printed = true;
+                            }
+                            for (position, (vert, e)) in top.enumerate() {
+                                println!(
+                                    "{},{},{},{:?},{:?},{:?}",
+                                    time.time(),
+                                    position + 1,
+                                    vert,
+                                    e.rank,
+                                    e.lower,
+                                    e.upper
+                                );
+                            }
+                        }
+                        Format::Json => {
+                            let top = top
+                                .map(|(vert, e)| {
+                                    json!({
+                                        "vertex": vert,
+                                        "rank": e.rank,
+                                        "lower": e.lower,
+                                        "upper": e.upper,
+                                    })
+                                })
+                                .collect::<Vec<_>>();
+                            println!("{}", json!({ "epoch": time.time(), "top": top }));
+                        }
+                    }
+                    estimates.clear();
+                }
+            }
+            estimate_stash.retain(|_key, val| !val.is_empty());
+        }
+    })
+}
@@ -0,0 +1,289 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::convergence::{self, Extrapolation, Gram, Residual};
+use crate::dataflow::{Params, Teleport, WarmStart};
+
+/// Single-threaded PageRank over dense vectors, with the same semantics as `dataflow::pagerank`:
+/// the same damping without redistributing the rank of dangling vertices, ranks summing up to
+/// about the number of vertices, and the same centrality, decay, teleport, schedule, extrapolation,
+/// warm start and stop rule.
+/// Meant as an oracle for the dataflow, and as a faster way to rank small graphs.
+pub struct ReferencePageRank {
+    params: Params,
+    teleport: Teleport,
+    // dense index of every vertex
+    index: HashMap<usize, usize>,
+    verts: Vec<usize>,
+    edges: Vec<Vec<(usize, u64)>>,
+    ranks: Vec<f64>,
+    // the epoch every vertex first appeared in, counting the calls to `advance`
+    born: Vec<u64>,
+    epoch: u64,
+    iterations: u64,
+    // the ranks of the three iterations before the last one, and the weights quadratic
+    // extrapolation takes from the last four
+    history: Vec<[f64; 3]>,
+    coefficients: Option<[f64; 3]>,
+}
+
+impl ReferencePageRank {
+    pub fn new(params: Params) -> Self {
+        ReferencePageRank {
+            params,
+            teleport: Teleport::Uniform,
+            index: HashMap::new(),
+            verts: Vec::new(),
+            edges: Vec::new(),
+            ranks: Vec::new(),
+            born: Vec::new(),
+            epoch: 0,
+            iterations: 0,
+            history: Vec::new(),
+            coefficients: None,
+        }
+    }
+
+    /// Jumps to the vertices `teleport` says only, as `dataflow::personalized_pagerank_from` does.
+    pub fn with_teleport(mut self, teleport: Teleport) -> Self {
+        self.teleport = teleport;
+        self
+    }
+
+    /// Adds the edges of a new epoch and iterates until the stop rule holds. Returns the rank of
+    /// every vertex, as `dataflow::pagerank` reports them for the epoch.
+    pub fn advance(&mut self, new_edges: &[(usize, usize)]) -> HashMap<usize, f64> {
+        // decide where known vertices and new vertices start from
+        let initial_rank = match self.params.warm_start {
+            WarmStart::Cold => {
+                self.ranks.iter_mut().for_each(|rank| *rank = 1.0);
+                1.0
+            }
+            WarmStart::Previous => 1.0,
+            WarmStart::Mean if !self.ranks.is_empty() => {
+                self.ranks.iter().sum::<f64>() / (self.ranks.len() as f64)
+            }
+            WarmStart::Mean => 1.0,
+            WarmStart::Teleport => self.params.teleport_start(),
+        };
+
+        for (src, dst) in new_edges {
+            let src = self.vertex(*src, initial_rank);
+            let dst = self.vertex(*dst, initial_rank);
+            self.edges[src].push((dst, self.epoch));
+        }
+
+        // iteration 0 only distributes the ranks, every later one also updates them
+        let stop_rule = self.params.stop_rule;
+        let mut iteration = 0;
+        while stop_rule.should_continue(iteration, &self.residual_of_update(iteration)) {
+            iteration += 1;
+        }
+        self.iterations = iteration;
+
+        // the dataflow reports the ranks the contributions of the last iteration lead to
+        let final_ranks = self.updated_ranks();
+        self.epoch += 1;
+        self.verts
+            .iter()
+            .zip(final_ranks)
+            .map(|(vert, rank)| (*vert, rank))
+            .collect()
+    }
+
+    /// Number of iterations the last epoch needed.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    fn vertex(&mut self, vert: usize, initial_rank: f64) -> usize {
+        let next = self.verts.len();
+        let index = *self.index.entry(vert).or_insert(next);
+        if index == next {
+            self.verts.push(vert);
+            self.edges.push(Vec::new());
+            self.ranks.push(initial_rank);
+            self.born.push(self.epoch);
+        }
+        index
+    }
+
+    /// Updates the ranks in every iteration but the first, and returns how much they changed.
+    fn residual_of_update(&mut self, iteration: u64) -> Residual {
+        let mut residual = Residual::default();
+        let extrapolation = self.params.extrapolation;
+        if iteration == 0 {
+            self.history = self.ranks.iter().map(|rank| [*rank; 3]).collect();
+            self.coefficients = None;
+        } else {
+            let mut new_ranks = if self.params.sweeps() {
+                self.swept_ranks()
+            } else {
+                self.updated_ranks()
+            };
+            if extrapolation.applies(iteration) {
+                self.extrapolate(&mut new_ranks);
+            }
+
+            let needs_gram = extrapolation.needs_gram(iteration);
+            let mut gram = Gram::default();
+            for ((new_rank, old_rank), earlier) in
+                new_ranks.iter().zip(&self.ranks).zip(&mut self.history)
+            {
+                residual.add(*new_rank, *old_rank);
+                if needs_gram {
+                    gram.add([earlier[1], earlier[2], *old_rank, *new_rank]);
+                }
+                *earlier = [earlier[1], earlier[2], *old_rank];
+            }
+            self.coefficients = if needs_gram {
+                gram.coefficients()
+            } else {
+                None
+            };
+            self.ranks = new_ranks;
+        }
+        residual
+    }
+
+    /// Replaces the `new_ranks` by their extrapolation from the ranks of the iterations before, as
+    /// `dataflow::pagerank` does.
+    fn extrapolate(&self, new_ranks: &mut [f64]) {
+        let earlier = self.history.iter().zip(&self.ranks);
+        for (new_rank, (earlier, old_rank)) in new_ranks.iter_mut().zip(earlier) {
+            *new_rank = match (self.params.extrapolation, self.coefficients) {
+                (Extrapolation::Aitken { .. }, _) => {
+                    convergence::aitken(earlier[2], *old_rank, *new_rank)
+                }
+                (Extrapolation::Quadratic { .. }, Some([b0, b1, b2])) => {
+                    b0 * earlier[2] + b1 * old_rank + b2 * *new_rank
+                }
+                _ => *new_rank,
+            };
+        }
+    }
+
+    fn updated_ranks(&self) -> Vec<f64> {
+        self.contribution_sums()
+            .into_iter()
+            .enumerate()
+            .map(|(vert, contribution)| self.rank_of(vert, contribution))
+            .collect()
+    }
+
+    /// Updates the ranks one after another from the highest vertex down, passing the change of each
+    /// on to the vertices it cites right away, as `Schedule::GaussSeidel` does with a single worker.
+    fn swept_ranks(&self) -> Vec<f64> {
+        let mut contribution_sum = self.contribution_sums();
+        let mut ranks = self.ranks.clone();
+        let mut order = (0..ranks.len()).collect::<Vec<_>>();
+        order.sort_unstable_by(|a, b| self.verts[*b].cmp(&self.verts[*a]));
+        for src in order {
+            let new_rank = self.rank_of(src, contribution_sum[src]);
+            let dsts = &self.edges[src];
+            let (weights, total_weight) = self.weights(dsts);
+            for ((dst, _), weight) in dsts.iter().zip(weights) {
+                contribution_sum[*dst] +=
+                    self.params
+                        .contribution(new_rank - ranks[src], weight, total_weight);
+            }
+            ranks[src] = new_rank;
+        }
+        ranks
+    }
+
+    /// What every vertex gets from the current ranks of the vertices citing it.
+    fn contribution_sums(&self) -> Vec<f64> {
+        let rank_sum: f64 = self.ranks.iter().sum();
+        let mut contribution_sum = vec![0.0f64; self.ranks.len()];
+        for (src, dsts) in self.edges.iter().enumerate() {
+            let mut rank = self.ranks[src];
+            if self.params.normalizes() {
+                if rank_sum > 0.0 {
+                    rank /= rank_sum;
+                }
+                contribution_sum[src] += rank;
+            }
+            let (weights, total_weight) = self.weights(dsts);
+            for ((dst, _), weight) in dsts.iter().zip(weights) {
+                contribution_sum[*dst] += self.params.contribution(rank, weight, total_weight);
+            }
+        }
+        contribution_sum
+    }
+
+    /// The weight of each of the citations `dsts` in the current epoch, and their sum.
+    fn weights(&self, dsts: &[(usize, u64)]) -> (Vec<f64>, f64) {
+        let weights = dsts
+            .iter()
+            .map(|(_, added)| self.params.decay.weight(self.epoch - added))
+            .collect::<Vec<_>>();
+        let total_weight = weights.iter().sum();
+        (weights, total_weight)
+    }
+
+    fn rank_of(&self, vert: usize, contribution: f64) -> f64 {
+        let share = self.teleport.share(self.verts[vert]);
+        self.params
+            .rank(contribution, self.epoch - self.born[vert], share)
+    }
+}
+
+/// How far one set of ranks is from another, after normalizing both to sum up to the number of
+/// vertices.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Deviation {
+    pub max: f64,
+    pub mean: f64,
+    /// share of the top `k` vertices of the expected ranks that are in the actual top `k`
+    pub top_k_overlap: f64,
+}
+
+impl Deviation {
+    pub fn between(expected: &HashMap<usize, f64>, actual: &HashMap<usize, f64>, k: usize) -> Self {
+        let expected = normalize(expected);
+        let actual = normalize(actual);
+
+        let mut deviation = Deviation::default();
+        for (vert, expected_rank) in &expected {
+            // a missing vertex deviates by its whole rank
+            let diff = (expected_rank - actual.get(vert).unwrap_or(&0.0)).abs();
+            deviation.max = deviation.max.max(diff);
+            deviation.mean += diff;
+        }
+        if !expected.is_empty() {
+            deviation.mean /= expected.len() as f64;
+        }
+
+        let expected_top = top_k(&expected, k);
+        let actual_top = top_k(&actual, k).into_iter().collect::<HashSet<_>>();
+        if !expected_top.is_empty() {
+            let overlap = expected_top
+                .iter()
+                .filter(|vert| actual_top.contains(*vert))
+                .count();
+            deviation.top_k_overlap = overlap as f64 / expected_top.len() as f64;
+        }
+        deviation
+    }
+}
+
+/// Scales `ranks` so they sum up to the number of vertices, like every solution reports them.
+pub fn normalize(ranks: &HashMap<usize, f64>) -> HashMap<usize, f64> {
+    let sum: f64 = ranks.values().sum();
+    let normalize_factor = ranks.len() as f64 / sum;
+    ranks
+        .iter()
+        .map(|(vert, rank)| (*vert, rank * normalize_factor))
+        .collect()
+}
+
+/// The `k` vertices with the highest ranks, from the highest down.
+pub fn top_k(ranks: &HashMap<usize, f64>, k: usize) -> Vec<usize> {
+    let mut rank_vec = Vec::from_iter(ranks);
+    rank_vec.sort_by(|&(_, a), &(_, b)| b.partial_cmp(a).unwrap());
+    rank_vec
+        .into_iter()
+        .take(k)
+        .map(|(vert, _)| *vert)
+        .collect()
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use serde_json::{json, Value};
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::OutputHandle;
+use timely::dataflow::{operators::*, Scope, Stream};
+
+/// The ranks of one completed epoch, normalized so that they sum up to the number of vertices unless
+/// they are all zero, and the same ranks sorted from the highest down.
+#[derive(Debug)]
+struct EpochRanks {
+    ranks: HashMap<usize, f64>,
+    sorted: Vec<(usize, f64)>,
+}
+
+impl EpochRanks {
+    fn new(mut sorted: Vec<(usize, f64)>) -> Self {
+        // the ranks may all be zero, such as TrustRank before any seed appears
+        let sum: f64 = sorted.iter().map(|(_, rank)| rank).sum();
+        if sum > 0.0 {
+            let normalize_factor = sorted.len() as f64 / sum;
+            for (_, rank) in sorted.iter_mut() {
+                *rank *= normalize_factor;
+            }
+        }
+        sorted.sort_by(|&(_, a), &(_, b)| b.total_cmp(&a));
+
+        EpochRanks {
+            ranks: sorted.iter().copied().collect(),
+            sorted,
+        }
+    }
+
+    /// The share of vertices with a lower rank than `rank`, in percent.
+    fn percentile(&self, rank: f64) -> f64 {
+        let below = self.sorted.len() - self.sorted.partition_point(|&(_, other)| other >= rank);
+        100.0 * below as f64 / self.sorted.len() as f64
+    }
+}
+
+/// The ranks of every completed epoch, published by the dataflow and read by the query server
+/// while the next epochs are being computed. Clones share the same ranks.
+#[derive(Clone, Debug, Default)]
+pub struct RankStore {
+    epochs: Arc<RwLock<BTreeMap<u64, Arc<EpochRanks>>>>,
+}
+
+impl RankStore {
+    pub fn new() -> Self {
+        RankStore::default()
+    }
+
+    /// Adds the final `ranks` of `epoch`, which queries answer from as long as no later epoch is
+    /// published.
+    pub fn publish(&self, epoch: u64, ranks: Vec<(usize, f64)>) {
+        let ranks = Arc::new(EpochRanks::new(ranks));
+        self.epochs.write().unwrap().insert(epoch, ranks);
+    }
+
+    /// The last epoch whose ranks were published.
+    pub fn latest_epoch(&self) -> Option<u64> {
+        self.epochs.read().unwrap().keys().next_back().copied()
+    }
+
+    fn latest(&self) -> Option<(u64, Arc<EpochRanks>)> {
+        let epochs = self.epochs.read().unwrap();
+        epochs
+            .iter()
+            .next_back()
+            .map(|(epoch, ranks)| (*epoch, ranks.clone()))
+    }
+
+    /// Answers a query of the line protocol with a single line of JSON:
+    ///
+    /// ```text
+    /// rank <vertex>      {"epoch":..,"vertex":..,"rank":..,"percentile":..}
+    /// top <k>            {"epoch":..,"top":[{"vertex":..,"rank":..},..]}
+    /// history <vertex>   {"vertex":..,"history":[{"epoch":..,"rank":..},..]}
+    /// ```
+    ///
+    /// `rank` and `top` answer from the latest completed epoch, `history` lists every completed
+    /// epoch the vertex is part of. Anything else is answered with `{"error":..}`.
+    pub fn answer(&self, query: &str) -> String {
+        self.try_answer(query)
+            .unwrap_or_else(|message| json!({ "error": message }))
+            .to_string()
+    }
+
+    fn try_answer(&self, query: &str) -> Result<Value, String> {
+        let fields = query.split_whitespace().collect::<Vec<_>>();
+        match fields[..] {
+            ["rank", vertex] => {
+                let vertex: usize = parse("vertex", vertex)?;
+                let (epoch, ranks) = self.latest().ok_or("no epoch has completed yet")?;
+                let rank = *ranks
+                    .ranks
+                    .get(&vertex)
+                    .ok_or_else(|| format!("vertex {} is not in epoch {}", vertex, epoch))?;
+                Ok(json!({
+                    "epoch": epoch,
+                    "vertex": vertex,
+                    "rank": rank,
+                    "percentile": ranks.percentile(rank)
+                }))
+            }
+            ["top", k] => {
+                let k: usize = parse("k", k)?;
+                let (epoch, ranks) = self.latest().ok_or("no epoch has completed yet")?;
+                let top = ranks
+                    .sorted
+                    .iter()
+                    .take(k)
+                    .map(|(vertex, rank)| json!({ "vertex": vertex, "rank": rank }))
+                    .collect::<Vec<_>>();
+                Ok(json!({ "epoch": epoch, "top": top }))
+            }
+            ["history", vertex] => {
+                let vertex: usize = parse("vertex", vertex)?;
+                let epochs = self.epochs.read().unwrap();
+                let history = epochs
+                    .iter()
+                    .filter_map(|(epoch, ranks)| {
+                        let rank = ranks.ranks.get(&vertex)?;
+                        Some(json!({ "epoch": epoch, "rank": rank }))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({ "vertex": vertex, "history": history }))
+            }
+            _ => Err(format!(
+                "unknown query '{}', expected rank <vertex>, top <k> or history <vertex>",
+                query.trim()
+            )),
+        }
+    }
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", name, value))
+}
+
+/// Gathers the `ranks` of each epoch on worker 0, and publishes them to `store` once the epoch is
+/// complete.
+pub fn publish_ranks<G>(ranks: &Stream<G, (usize, f64)>, store: RankStore) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    ranks.unary_frontier(
+        Exchange::new(|_| 0),
+        "Publish",
+        move |_capability, _info| {
+            let mut rank_stash = HashMap::new();
+            let mut rank_vec = Vec::new();
+
+            move |input, _output: &mut OutputHandle<u64, (), _>| {
+                input.for_each(|time, data| {
+                    data.swap(&mut rank_vec);
+                    rank_stash
+                        .entry(time.retain())
+                        .or_insert(Vec::new())
+                        .append(&mut rank_vec);
+                });
+
+                for (time, ranks) in rank_stash.iter_mut() {
+                    if !input.frontier.less_equal(time) {
+                        store.publish(*time.time(), std::mem::take(ranks));
+                    }
+                }
+                rank_stash.retain(|_key, val| !val.is_empty());
+            }
+        },
+    )
+}
+
+/// Answers the queries of every client connecting to `listener`, one per line, each on a thread of
+/// its own, until accepting a connection fails.
+pub fn serve(listener: TcpListener, store: RankStore) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let store = store.clone();
+        thread::spawn(move || {
+            // a client going away in the middle of a query only ends its own connection
+            let _ = answer_queries(stream, &store);
+        });
+    }
+    Ok(())
+}
+
+fn answer_queries(stream: TcpStream, store: &RankStore) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", store.answer(&line))?;
+    }
+    Ok(())
+}
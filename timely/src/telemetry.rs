@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use abomonation_derive::Abomonation;
+
+use crate::convergence::{Norm, Residual};
+
+/// How one iteration of an epoch went. Iteration 0 distributes the ranks after new edges arrived,
+/// so its residual is always empty.
+#[derive(Abomonation, Clone, Copy, Debug, PartialEq)]
+pub struct IterationStats {
+    pub iteration: u64,
+    pub residual: Residual,
+    /// bytes of contributions that all workers together sent to other workers for the next
+    /// iteration, not counting those a worker keeps for itself
+    pub exchanged_bytes: u64,
+    /// wall-clock seconds since iteration 0 of the epoch completed
+    pub elapsed: f64,
+}
+
+/// Collects the `IterationStats` of every epoch, to be printed once the run is over.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    epochs: BTreeMap<u64, Vec<IterationStats>>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Summary::default()
+    }
+
+    pub fn record(&mut self, epoch: u64, stats: IterationStats) {
+        let history = self.epochs.entry(epoch).or_default();
+        history.push(stats);
+        history.sort_by_key(|stats| stats.iteration);
+    }
+
+    /// The residual of each iteration of `epoch`, in order.
+    pub fn history(&self, epoch: u64) -> &[IterationStats] {
+        self.epochs.get(&epoch).map(|h| h.as_slice()).unwrap_or(&[])
+    }
+
+    /// Number of iterations `epoch` needed until its stop rule held.
+    pub fn iterations(&self, epoch: u64) -> u64 {
+        self.history(epoch).last().map(|s| s.iteration).unwrap_or(0)
+    }
+
+    /// Bytes of contributions exchanged between workers in all iterations of `epoch`.
+    pub fn exchanged_bytes(&self, epoch: u64) -> u64 {
+        self.history(epoch).iter().map(|s| s.exchanged_bytes).sum()
+    }
+
+    pub fn total_iterations(&self) -> u64 {
+        self.epochs
+            .keys()
+            .map(|epoch| self.iterations(*epoch))
+            .sum()
+    }
+
+    /// Prints one row per epoch with its iterations, final residual under `norm` and wall-clock time.
+    pub fn print(&self, norm: Norm) {
+        println!("--- convergence summary ({:?}) ---", norm);
+        println!(
+            "{:>6} {:>10} {:>14} {:>10}",
+            "epoch", "iterations", "residual", "seconds"
+        );
+        for (epoch, history) in &self.epochs {
+            if let Some(last) = history.last() {
+                println!(
+                    "{:>6} {:>10} {:>14.6e} {:>10.3}",
+                    epoch,
+                    last.iteration,
+                    last.residual.value(norm),
+                    last.elapsed
+                );
+            }
+        }
+        println!("total iterations: {}", self.total_iterations());
+    }
+}
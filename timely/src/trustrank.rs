@@ -0,0 +1,260 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::rc::Rc;
+
+use serde_json::json;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::OutputHandle;
+use timely::dataflow::{operators::*, Scope, Stream};
+
+use crate::dataflow::{self, Format, Params, State, Teleport};
+use crate::telemetry::IterationStats;
+
+/// Calculates the TrustRank of each epoch of `edges`, the PageRank where the random surfer only
+/// jumps to the `trusted` seeds, so that a vertex ranks high if trusted vertices cite it, directly
+/// or through others. A ring of vertices citing each other gains nothing unless the seeds reach it.
+/// Takes the same `params` and returns the same streams as `dataflow::pagerank`.
+pub fn trustrank<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    trusted: Teleport,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    trustrank_from(
+        edges,
+        params,
+        trusted,
+        Rc::new(RefCell::new(State::default())),
+    )
+}
+
+/// Same as `trustrank`, continuing from the `state` of this worker, see `dataflow::pagerank_from`.
+pub fn trustrank_from<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    trusted: Teleport,
+    state: Rc<RefCell<State>>,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    dataflow::personalized_pagerank_from(edges, params, trusted, state)
+}
+
+/// Calculates the anti-TrustRank of each epoch of `edges`, the TrustRank of the transposed graph
+/// from the `distrusted` seeds, so that a vertex ranks high if it cites distrusted vertices,
+/// directly or through others.
+pub fn anti_trustrank<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    distrusted: Teleport,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    anti_trustrank_from(
+        edges,
+        params,
+        distrusted,
+        Rc::new(RefCell::new(State::default())),
+    )
+}
+
+/// Same as `anti_trustrank`, continuing from the `state` of this worker, which holds the transposed
+/// graph, see `dataflow::pagerank_from`.
+pub fn anti_trustrank_from<G>(
+    edges: &Stream<G, (usize, usize)>,
+    params: Params,
+    distrusted: Teleport,
+    state: Rc<RefCell<State>>,
+) -> (Stream<G, (usize, f64)>, Stream<G, IterationStats>)
+where
+    G: Scope<Timestamp = u64>,
+{
+    let transposed = edges.map(|(src, dst)| (dst, src));
+    dataflow::personalized_pagerank_from(&transposed, params, distrusted, state)
+}
+
+/// Reads a set of seeds from `path`, one vertex per line. Empty lines and lines starting with `#`
+/// are skipped.
+pub fn read_seeds<P: AsRef<Path>>(path: P) -> io::Result<Vec<usize>> {
+    let mut seeds = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let seed = line.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("'{}' is not a vertex", line),
+            )
+        })?;
+        seeds.push(seed);
+    }
+    Ok(seeds)
+}
+
+/// A vertex whose PageRank is out of proportion to its TrustRank, both normalized so that they sum
+/// up to the number of vertices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anomaly {
+    pub vertex: usize,
+    pub pagerank: f64,
+    pub trustrank: f64,
+    /// `pagerank / trustrank`, infinite if the vertex has no TrustRank at all, and huge if the
+    /// trusted seeds do not reach it, whose TrustRank only keeps what is left of its starting rank
+    pub ratio: f64,
+}
+
+/// The vertices of an epoch whose PageRank is at least `min_ratio` times their TrustRank, the
+/// highest PageRank first, as vertices gaining rank from citation rings do. A vertex missing from
+/// `trustranks` has a TrustRank of 0.
+pub fn anomalies(
+    ranks: &[(usize, f64)],
+    trustranks: &[(usize, f64)],
+    min_ratio: f64,
+) -> Vec<Anomaly> {
+    let rank_sum: f64 = ranks.iter().map(|(_, rank)| rank).sum();
+    let trust_sum: f64 = trustranks.iter().map(|(_, trust)| trust).sum();
+    let trustranks = trustranks.iter().copied().collect::<HashMap<_, _>>();
+    let count = ranks.len() as f64;
+
+    let mut anomalies = ranks
+        .iter()
+        .filter(|(_, rank)| *rank > 0.0)
+        .map(|&(vertex, rank)| {
+            let pagerank = rank * count / rank_sum;
+            let trustrank = match trustranks.get(&vertex) {
+                Some(trust) if trust_sum > 0.0 => trust * count / trust_sum,
+                _ => 0.0,
+            };
+            Anomaly {
+                vertex,
+                pagerank,
+                trustrank,
+                ratio: pagerank / trustrank,
+            }
+        })
+        .filter(|anomaly| anomaly.ratio >= min_ratio)
+        .collect::<Vec<_>>();
+    anomalies.sort_by(|a, b| {
+        b.pagerank
+            .partial_cmp(&a.pagerank)
+            .unwrap()
+            .then(a.vertex.cmp(&b.vertex))
+    });
+    anomalies
+}
+
+/// Gathers the `ranks` and `trustranks` of each epoch on worker 0, and prints the top `k` of their
+/// `anomalies` with a ratio of at least `min_ratio`, in the given `format`.
+pub fn write_anomalies<G>(
+    ranks: &Stream<G, (usize, f64)>,
+    trustranks: &Stream<G, (usize, f64)>,
+    min_ratio: f64,
+    k: usize,
+    format: Format,
+) -> Stream<G, ()>
+where
+    G: Scope<Timestamp = u64>,
+{
+    ranks.binary_frontier(
+        trustranks,
+        Exchange::new(|_| 0),
+        Exchange::new(|_| 0),
+        "Anomalies",
+        move |_capability, _info| {
+            let mut rank_stash = HashMap::new();
+            let mut rank_vec = Vec::new();
+            let mut trustrank_vec = Vec::new();
+            let mut header_printed = false;
+
+            move |input1, input2, _output: &mut OutputHandle<u64, (), _>| {
+                // hold on to final ranks of both until it is time
+                input1.for_each(|time, data| {
+                    data.swap(&mut rank_vec);
+                    let (ranks, _) = rank_stash
+                        .entry(time.retain())
+                        .or_insert((Vec::new(), Vec::new()));
+                    ranks.append(&mut rank_vec);
+                });
+                input2.for_each(|time, data| {
+                    data.swap(&mut trustrank_vec);
+                    let (_, trustranks) = rank_stash
+                        .entry(time.retain())
+                        .or_insert((Vec::new(), Vec::new()));
+                    trustranks.append(&mut trustrank_vec);
+                });
+
+                let frontiers = &[input1.frontier(), input2.frontier()];
+                for (time, (ranks, trustranks)) in rank_stash.iter_mut() {
+                    if frontiers.iter().all(|f| !f.less_equal(time)) {
+                        let anomalies = anomalies(ranks, trustranks, min_ratio);
+                        let top = anomalies.iter().take(k);
+                        match format {
+                            Format::Text => {
+                                println!(
+                                    "--- year {:?} top {} with a PageRank to TrustRank ratio of \
+                                     at least {} ---",
+                                    time.time(),
+                                    k,
+                                    min_ratio
+                                );
+                                for a in top {
+                                    println!(
+                                        "{:?} has PageRank {:?} and TrustRank {:?}, ratio {:?}",
+                                        a.vertex, a.pagerank, a.trustrank, a.ratio
+                                    );
+                                }
+                            }
+                            Format::Csv => {
+                                if !header_printed {
+                                    println!("epoch,position,vertex,pagerank,trustrank,ratio");
+                                    header_printed = true;
+                                }
+                                for (position, a) in top.enumerate() {
+                                    println!(
+                                        "{},{},{},{:?},{:?},{:?}",
+                                        time.time(),
+                                        position + 1,
+                                        a.vertex,
+                                        a.pagerank,
+                                        a.trustrank,
+                                        a.ratio
+                                    );
+                                }
+                            }
+                            Format::Json => {
+                                // JSON has no infinity, a vertex the seeds do not reach has a
+                                // null ratio
+                                let top = top
+                                    .map(|a| {
+                                        json!({
+                                            "vertex": a.vertex,
+                                            "pagerank": a.pagerank,
+                                            "trustrank": a.trustrank,
+                                            "ratio": a.ratio,
+                                        })
+                                    })
+                                    .collect::<Vec<_>>();
+                                println!("{}", json!({ "epoch": time.time(), "anomalies": top }));
+                            }
+                        }
+                        ranks.clear();
+                        trustranks.clear();
+                    }
+                }
+                rank_stash.retain(|_key, (ranks, trustranks)| {
+                    !ranks.is_empty() || !trustranks.is_empty()
+                });
+            }
+        },
+    )
+}
@@ -1,30 +1,299 @@
 use std::{
+    cell::RefCell,
+    fmt::Debug,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader},
     path::Path,
+    rc::Rc,
+    str::FromStr,
 };
 
-const DATA_DIR_PATH: &str = "../dataset/incremental";
+use timely::communication::Allocate;
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::worker::Worker;
 
-pub fn get_citations_from_file(year: u64) -> Vec<(usize, usize)> {
-    let path = Path::new(DATA_DIR_PATH).join(format!("{}-edges.txt", year));
-    let file = File::open(path).unwrap();
+use crate::dataflow::{self, Params};
+use crate::telemetry::Summary;
+
+/// The partitioned HepPh dataset, relative to this crate, where the programs run from.
+pub const DATA_DIR_PATH: &str = "../dataset/incremental";
+/// First year of the partitioned HepPh dataset.
+pub const FIRST_YEAR: u64 = 1992;
+
+pub fn get_citations_from_file(year: u64) -> io::Result<Vec<(usize, usize)>> {
+    get_citations_from_dir(DATA_DIR_PATH, year)
+}
+
+pub fn get_citations_from_dir<P: AsRef<Path>>(
+    dir: P,
+    year: u64,
+) -> io::Result<Vec<(usize, usize)>> {
+    get_citation_shard_from_dir(dir, year, 0, 1)
+}
+
+/// The citations on every `peers`-th line of the file of `year` in `dir`, from line `index` on, so
+/// that every worker reads its own share of the year. Blank lines are skipped, while a line that
+/// cannot be read or does not hold two vertices fails along with the file and its line number.
+pub fn get_citation_shard_from_dir<P: AsRef<Path>>(
+    dir: P,
+    year: u64,
+    index: usize,
+    peers: usize,
+) -> io::Result<Vec<(usize, usize)>> {
+    let path = dir.as_ref().join(format!("{}-edges.txt", year));
+    let file = File::open(&path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
     let reader = BufReader::new(file);
 
     let mut citations = Vec::new();
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            let fields = line
-                .split_whitespace()
-                .map(|s| s.parse::<usize>().unwrap())
-                .take(2)
-                .collect::<Vec<usize>>();
-            let src_id = fields[0];
-            let dst_id = fields[1];
-
-            citations.push((src_id, dst_id));
+    for (number, line) in reader.lines().enumerate().skip(index).step_by(peers) {
+        let line_error = |kind, message: &str| {
+            io::Error::new(
+                kind,
+                format!("{}:{}: {}", path.display(), number + 1, message),
+            )
+        };
+        let line = line.map_err(|err| line_error(err.kind(), &err.to_string()))?;
+        let fields = line
+            .split_whitespace()
+            .take(2)
+            .map(|s| s.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| line_error(io::ErrorKind::InvalidData, &err.to_string()))?;
+        match fields[..] {
+            [] => continue,
+            [src_id, dst_id] => citations.push((src_id, dst_id)),
+            _ => {
+                return Err(line_error(
+                    io::ErrorKind::InvalidData,
+                    "expected a citing and a cited vertex",
+                ))
+            }
         }
     }
 
-    citations
+    Ok(citations)
+}
+
+/// The citations of every year from `start` to `end` in `DATA_DIR_PATH`, where `snapshot` puts all
+/// years since `FIRST_YEAR` into the batch of `start`, see `start_at`.
+pub fn get_years_from_file(
+    start: u64,
+    end: u64,
+    snapshot: bool,
+) -> io::Result<impl Iterator<Item = (u64, Vec<(usize, usize)>)>> {
+    get_year_shards_from_file(start, end, snapshot, 0, 1)
+}
+
+/// Same as `get_years_from_file`, with only the share of worker `index` of `peers` of every year.
+pub fn get_year_shards_from_file(
+    start: u64,
+    end: u64,
+    snapshot: bool,
+    index: usize,
+    peers: usize,
+) -> io::Result<impl Iterator<Item = (u64, Vec<(usize, usize)>)>> {
+    let first = if snapshot {
+        FIRST_YEAR.min(start)
+    } else {
+        start
+    };
+    get_year_shards_from_dir(DATA_DIR_PATH, first, start, end, index, peers)
+}
+
+/// The citations of every year from `first` to `end` in `dir`, numbered from `start` on as
+/// `start_at` does. All years are read up front, so that none of them is left out by an error.
+pub fn get_years_from_dir<P: AsRef<Path>>(
+    dir: P,
+    first: u64,
+    start: u64,
+    end: u64,
+) -> io::Result<impl Iterator<Item = (u64, Vec<(usize, usize)>)>> {
+    get_year_shards_from_dir(dir, first, start, end, 0, 1)
+}
+
+/// Same as `get_years_from_dir`, with only the share of worker `index` of `peers` of every year.
+pub fn get_year_shards_from_dir<P: AsRef<Path>>(
+    dir: P,
+    first: u64,
+    start: u64,
+    end: u64,
+    index: usize,
+    peers: usize,
+) -> io::Result<impl Iterator<Item = (u64, Vec<(usize, usize)>)>> {
+    let batches = (first..=end)
+        .map(|year| get_citation_shard_from_dir(&dir, year, index, peers))
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(start_at(batches, first, start))
+}
+
+/// Numbers `batches`, the new edges of every epoch from `first` on, and puts the batches of all
+/// epochs before `start` into the batch of `start`. The graph up to `start` is loaded as a single
+/// snapshot that way, and ranks are only reported from `start` on.
+pub fn start_at<I>(
+    batches: I,
+    first: u64,
+    start: u64,
+) -> impl Iterator<Item = (u64, Vec<(usize, usize)>)>
+where
+    I: IntoIterator<Item = Vec<(usize, usize)>>,
+{
+    let mut batches = batches.into_iter();
+    let mut snapshot = Vec::new();
+    for _ in first..start {
+        snapshot.extend(batches.next().unwrap_or_default());
+    }
+
+    (start..).zip(batches).map(move |(epoch, mut edges)| {
+        if epoch == start {
+            snapshot.append(&mut edges);
+            edges = std::mem::take(&mut snapshot);
+        }
+        (epoch, edges)
+    })
+}
+
+/// Where the examples start: the year given as `--start=<year>`, or `FIRST_YEAR`, and whether
+/// `--snapshot` asks to load the years before it at once.
+pub fn start_from_args() -> (u64, bool) {
+    let start = std::env::args()
+        .find_map(|arg| {
+            arg.strip_prefix("--start=")
+                .map(|year| year.parse().unwrap())
+        })
+        .unwrap_or(FIRST_YEAR);
+    let snapshot = std::env::args().any(|arg| arg == "--snapshot");
+    (start, snapshot)
+}
+
+/// The command-line arguments without the flags only the examples know, which timely would reject.
+pub fn timely_args() -> impl Iterator<Item = String> {
+    std::env::args().filter(|arg| {
+        !(arg == "--snapshot"
+            || arg == "--summary"
+            || arg.starts_with("--start=")
+            || arg.starts_with("--warm-start="))
+    })
+}
+
+/// The `n`-th command-line argument of an example, unless it is missing or a flag. Panics naming
+/// the argument if it does not parse.
+pub fn positional_arg<T>(n: usize) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Debug,
+{
+    std::env::args()
+        .nth(n)
+        .filter(|arg| !arg.starts_with('-'))
+        .map(|arg| {
+            arg.parse()
+                .unwrap_or_else(|err| panic!("invalid argument '{}': {:?}", arg, err))
+        })
+}
+
+/// Sends the edges of every one of `years` to `input`, and steps `worker` until `probe` has seen
+/// each year through before the next one.
+pub fn feed_years<A, I>(
+    worker: &mut Worker<A>,
+    input: &mut InputHandle<u64, (usize, usize)>,
+    probe: &ProbeHandle<u64>,
+    years: I,
+) where
+    A: Allocate,
+    I: IntoIterator<Item = (u64, Vec<(usize, usize)>)>,
+{
+    for (year, edges) in years {
+        for e in edges {
+            input.send(e);
+        }
+        input.advance_to(year + 1);
+
+        worker.step_while(|| probe.less_than(input.time()));
+    }
+}
+
+/// Runs `dataflow::pagerank` over the years up to `end_year` once per variant, with the `Params`
+/// that `params` makes of it, and prints the iterations every variant needs per year and in total,
+/// along with the iterations it saves compared to the first variant. Worker 0 reads the years and
+/// collects the telemetry, starting where `start_from_args` says.
+pub fn compare_iterations<V, F>(variants: &[V], end_year: u64, params: F)
+where
+    V: Debug + Copy + Send + Sync + 'static,
+    F: Fn(V) -> Params + Copy + Send + Sync + 'static,
+{
+    // the first year to report, with the years before it loaded at once when asked for
+    let (start_year, snapshot) = start_from_args();
+
+    let mut summaries = Vec::new();
+    for &variant in variants {
+        let guards = timely::execute_from_args(timely_args(), move |worker| {
+            let mut input = InputHandle::new();
+            let mut probe = ProbeHandle::new();
+            let summary = Rc::new(RefCell::new(Summary::new()));
+
+            worker.dataflow::<u64, _, _>(|scope| {
+                let edge_stream = input.to_stream(scope);
+                let (single_year, stats) = dataflow::pagerank(&edge_stream, params(variant));
+
+                let summary = summary.clone();
+                stats.inspect_batch(move |time, data| {
+                    for stats in data {
+                        summary.borrow_mut().record(*time, *stats);
+                    }
+                });
+                single_year.probe_with(&mut probe);
+            });
+
+            input.advance_to(start_year);
+            if worker.index() == 0 {
+                let years = get_years_from_file(start_year, end_year, snapshot)
+                    .expect("cannot read the dataset");
+                feed_years(worker, &mut input, &probe, years);
+            }
+
+            let summary = summary.borrow().clone();
+            summary
+        })
+        .unwrap();
+
+        // only worker 0 collects the telemetry
+        let summary = guards.join().remove(0).unwrap();
+        summaries.push((format!("{:?}", variant), summary));
+    }
+
+    let width = summaries
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    println!("--- iterations per year ---");
+    print!("{:>6}", "year");
+    for (name, _) in &summaries {
+        print!(" {:>width$}", name, width = width);
+    }
+    println!();
+    for year in start_year..=end_year {
+        print!("{:>6}", year);
+        for (_, summary) in &summaries {
+            print!(" {:>width$}", summary.iterations(year), width = width);
+        }
+        println!();
+    }
+
+    let (baseline_name, baseline) = &summaries[0];
+    let baseline = baseline.total_iterations() as f64;
+    println!("--- total iterations ---");
+    for (name, summary) in &summaries {
+        let total = summary.total_iterations();
+        println!(
+            "{}: {} ({:.1}% saved compared to {})",
+            name,
+            total,
+            100.0 * (baseline - total as f64) / baseline,
+            baseline_name
+        );
+    }
 }
@@ -0,0 +1,100 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, Schedule, WarmStart};
+use pagerank::reference::ReferencePageRank;
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank_summarized, Ranks};
+
+/// `citation_graph` with every third citation returned, so that some papers end up in blocks
+/// citing each other.
+fn cyclic_graph(epochs: usize, papers: usize, citations: usize) -> Vec<Vec<(usize, usize)>> {
+    citation_graph(epochs, papers, citations)
+        .into_iter()
+        .map(|edges| {
+            let returned = edges
+                .iter()
+                .step_by(3)
+                .map(|&(src, dst)| (dst, src))
+                .collect::<Vec<_>>();
+            edges.into_iter().chain(returned).collect()
+        })
+        .collect()
+}
+
+fn run_reference(epochs: &[Vec<(usize, usize)>], params: Params) -> Ranks {
+    let mut reference = ReferencePageRank::new(params);
+    let mut ranks = BTreeMap::new();
+    for (epoch, edges) in epochs.iter().enumerate() {
+        ranks.insert(epoch as u64, reference.advance(edges));
+    }
+    ranks
+}
+
+#[test]
+fn converges_to_the_same_ranks() {
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+    for epochs in [citation_graph(4, 200, 5), cyclic_graph(3, 150, 4)] {
+        let expected = run_reference(&epochs, params);
+        for workers in [1, 3] {
+            let (actual, _) =
+                run_pagerank_summarized(&epochs, workers, params.with_schedule(Schedule::Blocks));
+            assert_ranks_eq(&expected, &actual, 1e-8);
+        }
+    }
+}
+
+#[test]
+fn merges_the_blocks_new_citations_close_a_cycle_through() {
+    let epochs = vec![
+        // a chain of blocks of a single paper each
+        vec![(1, 0), (2, 1), (3, 2), (4, 0)],
+        // 0 citing 3 merges the chain into one block, 4 stays on its own
+        vec![(0, 3), (5, 4)],
+        // a new paper joining the block, and another one a block of its own with 4
+        vec![(6, 2), (1, 6), (7, 4), (4, 7), (7, 6)],
+    ];
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+    let expected = run_reference(&epochs, params);
+    for workers in [1, 3] {
+        let (actual, _) =
+            run_pagerank_summarized(&epochs, workers, params.with_schedule(Schedule::Blocks));
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn starts_where_the_warm_start_says() {
+    let epochs = cyclic_graph(3, 150, 4);
+    for warm_start in [WarmStart::Cold, WarmStart::Mean, WarmStart::Teleport] {
+        let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12)).with_warm_start(warm_start);
+        let expected = run_reference(&epochs, params);
+        let (actual, _) =
+            run_pagerank_summarized(&epochs, 2, params.with_schedule(Schedule::Blocks));
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn needs_at_most_as_many_iterations_as_workers() {
+    let epochs = cyclic_graph(4, 200, 5);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-10));
+
+    let (_, jacobi) = run_pagerank_summarized(&epochs, 3, params);
+    let params = params.with_schedule(Schedule::Blocks);
+    for workers in [1, 3] {
+        let (_, blocks) = run_pagerank_summarized(&epochs, workers, params);
+        for epoch in 0..epochs.len() as u64 {
+            assert!(
+                blocks.iterations(epoch) <= workers as u64,
+                "epoch {} took {} iterations with {} workers",
+                epoch,
+                blocks.iterations(epoch),
+                workers
+            );
+        }
+        assert!(blocks.total_iterations() < jacobi.total_iterations());
+    }
+}
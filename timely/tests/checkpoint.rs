@@ -0,0 +1,120 @@
+mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use pagerank::checkpoint::Checkpoint;
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, State};
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank, run_pagerank_checkpointed};
+
+/// An empty directory of its own for every test.
+fn checkpoint_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pagerank-checkpoint-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn state_survives_a_different_number_of_workers() {
+    let dir = checkpoint_dir("workers");
+    let checkpoint = Checkpoint::new(&dir);
+
+    // what two workers own after an epoch, by `vertex % 2`
+    let states = [
+        State {
+            edges: HashMap::from([(0, vec![(1, 1994), (3, 1994)]), (2, vec![(1, 1993)])]),
+            ranks: HashMap::from([(0, 0.15), (2, 0.15), (4, 0.7000000000000001)]),
+            born: HashMap::from([(0, 1994), (2, 1993), (4, 1993)]),
+            total: (1.57625, 5),
+        },
+        State {
+            edges: HashMap::from([(3, vec![(0, 1994)])]),
+            ranks: HashMap::from([(1, 0.3625), (3, 0.2137500000000001)]),
+            born: HashMap::from([(1, 1992), (3, 1994)]),
+            total: (1.57625, 5),
+        },
+    ];
+    for (index, state) in states.iter().enumerate() {
+        checkpoint.save(1994, index, 2, state).unwrap();
+    }
+    assert_eq!(checkpoint.latest().unwrap(), Some(1994));
+
+    // a single worker owns everything
+    let single = checkpoint.load(1994, 0, 1).unwrap();
+    assert_eq!(single.ranks.len(), 5);
+    assert_eq!(single.ranks[&4], 0.7000000000000001);
+    assert_eq!(single.edges[&0], [(1, 1994), (3, 1994)]);
+    assert_eq!(single.born[&1], 1992);
+    assert_eq!(single.total.1, 5);
+
+    // three workers split it by `vertex % 3`
+    let parts = (0..3)
+        .map(|index| checkpoint.load(1994, index, 3).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(parts.iter().map(|p| p.ranks.len()).sum::<usize>(), 5);
+    assert!(parts[0].edges.contains_key(&0) && parts[0].edges.contains_key(&3));
+    assert!(parts[1].ranks.contains_key(&1) && parts[1].ranks.contains_key(&4));
+    assert_eq!(parts[2].edges[&2], [(1, 1993)]);
+    // every part knows the total of all ranks
+    assert!(parts.iter().all(|part| part.total == single.total));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn incomplete_epochs_are_not_restored() {
+    let dir = checkpoint_dir("incomplete");
+    let checkpoint = Checkpoint::new(&dir);
+    assert_eq!(checkpoint.latest().unwrap(), None);
+
+    for index in 0..2 {
+        checkpoint.save(3, index, 2, &State::default()).unwrap();
+    }
+    // only one of two workers got to save epoch 4
+    checkpoint.save(4, 0, 2, &State::default()).unwrap();
+    assert_eq!(checkpoint.latest().unwrap(), Some(3));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn restored_run_continues_like_an_uninterrupted_one() {
+    let dir = checkpoint_dir("restore");
+    let epochs = citation_graph(5, 80, 4);
+
+    for stop_rule in [
+        StopRule::iterations(20),
+        StopRule::tolerance(Norm::L1, 1e-10),
+    ] {
+        let _ = fs::remove_dir_all(&dir);
+        let mut expected = run_pagerank(&epochs, 4, stop_rule);
+        expected.retain(|epoch, _| *epoch >= 3);
+
+        // stop after epoch 2 with four workers, and continue with two
+        let checkpoint = Checkpoint::new(&dir);
+        run_pagerank_checkpointed(
+            0,
+            &epochs[..3],
+            4,
+            Params::new(stop_rule),
+            Some(checkpoint.clone()),
+            None,
+        );
+        assert_eq!(checkpoint.latest().unwrap(), Some(2));
+        let actual = run_pagerank_checkpointed(
+            3,
+            &epochs[3..],
+            2,
+            Params::new(stop_rule),
+            Some(checkpoint),
+            Some(2),
+        );
+
+        assert_ranks_eq(&expected, &actual, 1e-9);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
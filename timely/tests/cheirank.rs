@@ -0,0 +1,126 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::cheirank::{self, Positions};
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::Params;
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank, Ranks};
+
+/// Runs `cheirank::cheirank` over `epochs` with `workers` worker threads, and collects the ranks
+/// reported by all workers.
+fn run_cheirank(epochs: &[Vec<(usize, usize)>], workers: usize, stop_rule: StopRule) -> Ranks {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = cheirank::cheirank(&edge_stream, Params::new(stop_rule));
+
+            let results = results.clone();
+            ranks
+                .inspect_batch(move |time, data| {
+                    for (vert, rank) in data {
+                        results.borrow_mut().push((*time, *vert, *rank));
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    let mut ranks = Ranks::new();
+    for result in guards.join() {
+        for (epoch, vert, rank) in result.unwrap() {
+            ranks.entry(epoch).or_default().insert(vert, rank);
+        }
+    }
+    ranks
+}
+
+#[test]
+fn cheirank_is_pagerank_of_the_transposed_graph() {
+    let epochs = citation_graph(3, 120, 4);
+    let transposed = epochs
+        .iter()
+        .map(|edges| edges.iter().map(|&(src, dst)| (dst, src)).collect())
+        .collect::<Vec<_>>();
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-10);
+
+    let expected = run_pagerank(&transposed, 1, stop_rule);
+    let actual = run_cheirank(&epochs, 3, stop_rule);
+    assert_ranks_eq(&expected, &actual, 1e-9);
+}
+
+#[test]
+fn review_articles_top_the_cheirank() {
+    // 5 cites every other paper, and 0 is cited by all of them
+    let epochs = vec![vec![
+        (1, 0),
+        (2, 0),
+        (3, 0),
+        (4, 0),
+        (5, 1),
+        (5, 2),
+        (5, 3),
+        (5, 4),
+        (5, 0),
+    ]];
+    let ranks = run_cheirank(&epochs, 2, StopRule::tolerance(Norm::L1, 1e-12));
+
+    let top = ranks[&0]
+        .iter()
+        .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+        .unwrap();
+    assert_eq!(*top.0, 5);
+    // 0 cites nothing
+    assert!((ranks[&0][&0] - 0.15).abs() < 1e-12);
+}
+
+#[test]
+fn orders_by_the_larger_position_first() {
+    let ranks = [(10, 4.0), (11, 3.0), (12, 2.0), (13, 1.0)];
+    let cheiranks = [(10, 1.0), (11, 4.0), (12, 3.0), (13, 2.0)];
+    let order = cheirank::order_2d(&ranks, &cheiranks);
+
+    let positions = |vertex, pagerank, cheirank| Positions {
+        vertex,
+        pagerank,
+        cheirank,
+    };
+    assert_eq!(
+        order,
+        [
+            // 11 is in the top 2 of both and 12 in the top 3, the others are only in the top 4
+            positions(11, 2, 1),
+            positions(12, 3, 2),
+            // 10 is first by PageRank
+            positions(10, 1, 4),
+            positions(13, 4, 3),
+        ]
+    );
+}
@@ -0,0 +1,392 @@
+use std::path::Path;
+
+use pagerank::cli::{Args, Error, Measure, Source};
+use pagerank::config::Config;
+use pagerank::convergence::{Extrapolation, Norm};
+use pagerank::dataflow::{Centrality, Decay, Format, Schedule, State, Teleport, WarmStart};
+use pagerank::generators::Model;
+
+const DATA_DIR: &str = "tests/fixtures/incremental";
+
+fn parse(args: &str) -> Result<Args, Error> {
+    Args::parse(args.split_whitespace().map(String::from))
+}
+
+fn message(result: Result<Args, Error>) -> String {
+    match result {
+        Err(Error::Invalid(message)) => message,
+        other => panic!("expected an invalid argument, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_iterations_mode() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --start 1993 --end=1995 --data-dir {} \
+         --damping 0.9 --top-k 3 --format csv --warm-start cold --summary",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.stop_rule.max_iterations, Some(20));
+    assert_eq!(args.params.stop_rule.tolerance, None);
+    assert_eq!(args.params.warm_start, WarmStart::Cold);
+    assert_eq!(args.params.damping, 0.9);
+    assert_eq!((args.start, args.end), (1993, 1995));
+    assert_eq!(args.top_k, 3);
+    assert_eq!(args.format, Format::Csv);
+    assert!(args.summary);
+}
+
+#[test]
+fn parses_tolerance_mode() {
+    let args = parse(&format!(
+        "pagerank tolerance --tolerance 1e-10 --norm l1 --max-iterations 50 --end 1994 \
+         --data-dir {}",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.stop_rule.norm, Norm::L1);
+    assert_eq!(args.params.stop_rule.tolerance, Some(1e-10));
+    assert_eq!(args.params.stop_rule.max_iterations, Some(50));
+    assert_eq!(args.params.warm_start, WarmStart::Previous);
+    assert_eq!(args.format, Format::Text);
+}
+
+#[test]
+fn parses_centrality_measures() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure katz --alpha 0.05",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.measure, Measure::Katz);
+    assert_eq!(
+        args.params.centrality,
+        Centrality::Katz {
+            alpha: 0.05,
+            beta: 1.0
+        }
+    );
+
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure eigenvector",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.centrality, Centrality::Eigenvector);
+    assert_eq!(args.measure.name(), "eigenvector");
+
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure katz --beta 0",
+        DATA_DIR
+    )))
+    .contains("beta"));
+}
+
+#[test]
+fn parses_citation_decays() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --decay exponential:2.5",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.decay, Decay::Exponential { tau: 2.5 });
+    assert_eq!("window:3".parse::<Decay>(), Ok(Decay::Window { epochs: 3 }));
+    assert_eq!("none".parse::<Decay>(), Ok(Decay::None));
+    assert_eq!(Decay::Power { exponent: 1.0 }.weight(3), 0.25);
+
+    for decay in ["exponential", "power:-1", "window:1.5", "linear:2"] {
+        assert!(message(parse(&format!(
+            "pagerank iterations --iterations 20 --end 1993 --data-dir {} --decay {}",
+            DATA_DIR, decay
+        )))
+        .contains("decay"));
+    }
+}
+
+#[test]
+fn reads_trusted_seeds() {
+    let path = std::env::temp_dir().join("pagerank-trusted-seeds.txt");
+    std::fs::write(&path, "# reviewed by hand\n9201001\n\n9201002\n").unwrap();
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure trustrank \
+         --trusted {} --anomaly-ratio 4",
+        DATA_DIR,
+        path.display()
+    ));
+
+    std::fs::write(&path, "# nothing reviewed yet\n").unwrap();
+    let empty = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure trustrank \
+         --trusted {}",
+        DATA_DIR,
+        path.display()
+    ));
+    std::fs::remove_file(&path).unwrap();
+
+    let args = args.unwrap();
+    assert_eq!(args.measure, Measure::TrustRank);
+    assert_eq!(args.teleport, Teleport::seeds([9201001, 9201002]));
+    assert_eq!(args.anomaly_ratio, 4.0);
+    assert!(message(empty).contains("no trusted seeds"));
+
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure anti-trustrank",
+        DATA_DIR
+    )))
+    .contains("--distrusted"));
+}
+
+#[test]
+fn parses_random_walks() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure montecarlo \
+         --walks 64 --seed 5",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.measure, Measure::MonteCarlo);
+    assert_eq!((args.walks, args.seed), (64, 5));
+
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --measure montecarlo \
+         --walks 0",
+        DATA_DIR
+    )))
+    .contains("walks"));
+}
+
+#[test]
+fn passes_worker_settings_to_timely() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 5 --end 1992 --data-dir {} --workers 4 -w2 --report",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.timely_args, ["pagerank", "-w", "2", "--report"]);
+}
+
+#[test]
+fn echoes_timely_worker_flags() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 5 --end 1992 --data-dir {} -w 3 -n2 -p 1 -h hosts",
+        DATA_DIR
+    ))
+    .unwrap();
+    let topology = &args.config.topology;
+    assert_eq!((topology.workers, topology.processes), (Some(3), Some(2)));
+    assert_eq!(topology.process, Some(1));
+    assert_eq!(topology.hostfile.as_deref(), Some(Path::new("hosts")));
+    assert_eq!(
+        args.timely_args,
+        ["pagerank", "-w", "3", "-n", "2", "-p", "1", "-h", "hosts"]
+    );
+}
+
+#[test]
+fn recognizes_launched_processes() {
+    let launcher = parse(&format!(
+        "pagerank iterations --iterations 5 --end 1992 --data-dir {} --local-processes 3",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!((launcher.launch, launcher.launched), (Some(3), false));
+
+    let launched = parse(&format!(
+        "pagerank iterations --iterations 5 --end 1992 --data-dir {} --local-processes 3 \
+         --processes 3 --process 1 --hostfile hosts",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!((launched.launch, launched.launched), (None, true));
+    assert_eq!(
+        launched.timely_args,
+        ["pagerank", "-n", "3", "-p", "1", "-h", "hosts"]
+    );
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    let data_dir = format!("--data-dir {}", DATA_DIR);
+    assert!(message(parse(&format!("pagerank --end 1993 {}", data_dir))).contains("mode"));
+    assert!(message(parse(&format!(
+        "pagerank tolerance --end 1993 {}",
+        data_dir
+    )))
+    .contains("--tolerance"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --norm l1 --end 1993 {}",
+        data_dir
+    )))
+    .contains("tolerance mode"));
+    assert!(message(parse(&format!(
+        "pagerank tolerance --tolerance 1e-5 --norm l3 --end 1993 {}",
+        data_dir
+    )))
+    .contains("unknown norm 'l3'"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --start 1994 --end 1993 {}",
+        data_dir
+    )))
+    .contains("before"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --damping 1.5 --end 1993 {}",
+        data_dir
+    )))
+    .contains("damping"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --end 1996 {}",
+        data_dir
+    )))
+    .contains("1996-edges.txt"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --end 1993 --local-processes 2 --hostfile hosts {}",
+        data_dir
+    )))
+    .contains("hostfile"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 5 --end 1993 --measure 2d --checkpoint-dir c {}",
+        data_dir
+    )))
+    .contains("state of 2d"));
+    assert!(message(parse("pagerank iterations --iterations")).contains("needs a value"));
+    assert_eq!(parse("pagerank --help").unwrap_err(), Error::Help);
+}
+
+#[test]
+fn flags_override_config_file() {
+    let args =
+        parse("pagerank --config tests/fixtures/config.toml --end 1994 --damping 0.8").unwrap();
+    assert_eq!(args.params.stop_rule.norm, Norm::L1);
+    assert_eq!(args.params.stop_rule.tolerance, Some(1e-8));
+    assert_eq!(args.params.damping, 0.8);
+    assert_eq!((args.start, args.end), (1992, 1994));
+    assert_eq!(args.format, Format::Json);
+    assert_eq!(args.timely_args, ["pagerank", "-w", "2"]);
+
+    // the echoed config describes the run with the overrides
+    assert_eq!(args.config.input.end, Some(1994));
+    assert_eq!(args.config.algorithm.damping, 0.8);
+}
+
+#[test]
+fn echoed_config_reproduces_the_run() {
+    let args = parse(&format!(
+        "pagerank tolerance --tolerance 1e-6 --end 1993 --data-dir {} --top-k 7 --workers 3",
+        DATA_DIR
+    ))
+    .unwrap();
+
+    let path = std::env::temp_dir().join("pagerank-echoed-config.toml");
+    std::fs::write(&path, args.config.to_toml()).unwrap();
+    let loaded = Config::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, args.config);
+
+    let reproduced = Args::from_config(loaded, Vec::new()).unwrap();
+    assert_eq!(reproduced.params.stop_rule.tolerance, Some(1e-6));
+    assert_eq!(reproduced.top_k, 7);
+    assert_eq!(reproduced.config.topology.workers, Some(3));
+}
+
+#[test]
+fn loads_json_configs() {
+    let config = Config::load("configs/synthetic_rmat.json").unwrap();
+    let args = Args::from_config(config, Vec::new()).unwrap();
+    assert_eq!(
+        args.source,
+        Source::Synthetic {
+            model: Model::RMat,
+            scale: 10.0,
+            seed: 7
+        }
+    );
+    assert_eq!((args.start, args.end), (0, 10));
+    assert_eq!(args.params.stop_rule.max_iterations, Some(100));
+}
+
+#[test]
+fn rejects_unknown_config_keys() {
+    let path = std::env::temp_dir().join("pagerank-unknown-key.toml");
+    std::fs::write(&path, "[stop]\nmode = \"tolerance\"\ntolerence = 1e-8\n").unwrap();
+    let result = Config::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.unwrap_err().contains("tolerence"));
+}
+
+#[test]
+fn parses_schedule() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --schedule gauss-seidel",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.schedule, Schedule::GaussSeidel);
+
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --schedule gauss-seidel \
+         --measure eigenvector",
+        DATA_DIR
+    )))
+    .contains("gauss-seidel"));
+
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --schedule blocks",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(args.params.schedule, Schedule::Blocks);
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --schedule blocks \
+         --extrapolation aitken:3",
+        DATA_DIR
+    )))
+    .contains("extrapolate"));
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --schedule blocks \
+         --checkpoint-dir c",
+        DATA_DIR
+    )))
+    .contains("state of blocks"));
+}
+
+#[test]
+fn parses_extrapolation() {
+    let args = parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --extrapolation quadratic:6",
+        DATA_DIR
+    ))
+    .unwrap();
+    assert_eq!(
+        args.params.extrapolation,
+        Extrapolation::Quadratic { period: 6 }
+    );
+
+    assert!(message(parse(&format!(
+        "pagerank iterations --iterations 20 --end 1993 --data-dir {} --extrapolation aitken:1",
+        DATA_DIR
+    )))
+    .contains("period"));
+}
+
+#[test]
+fn restores_only_the_state_of_the_same_run() {
+    let dir = std::env::temp_dir().join("pagerank-cli-restore");
+    let _ = std::fs::remove_dir_all(&dir);
+    let run = format!(
+        "pagerank tolerance --tolerance 1e-8 --end 1993 --data-dir {} --measure cheirank \
+         --checkpoint-dir {}",
+        DATA_DIR,
+        dir.display()
+    );
+    let checkpoint = parse(&run).unwrap().checkpoint.unwrap();
+    checkpoint.save(1993, 0, 1, &State::default()).unwrap();
+
+    // the same run goes on, while the transposed graph of cheirank is no state of pagerank
+    let restored = parse(&format!("{} --end 1995 --restore", run)).unwrap();
+    assert_eq!(restored.restored, Some(1993));
+    let other = run.replace("cheirank", "pagerank");
+    assert!(message(parse(&format!("{} --end 1995 --restore", other))).contains("algorithm"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
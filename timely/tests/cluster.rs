@@ -0,0 +1,91 @@
+use std::collections::{BTreeMap, HashSet};
+use std::process::Command;
+
+use pagerank::cluster;
+
+const DATA_DIR: &str = "tests/fixtures/incremental";
+
+/// Runs the `pagerank` binary and returns what it printed, which must be a single CSV header and
+/// config, and the ranks it reported per epoch and vertex.
+fn run(extra_args: &[&str]) -> BTreeMap<(u64, usize), f64> {
+    let output = Command::new(env!("CARGO_BIN_EXE_pagerank"))
+        .args([
+            "tolerance",
+            "--tolerance",
+            "1e-10",
+            "--norm",
+            "l1",
+            "--end",
+            "1995",
+            "--data-dir",
+            DATA_DIR,
+            // every vertex, so that no ties decide which ones are compared
+            "--top-k",
+            "1000",
+            "--format",
+            "csv",
+        ])
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.iter().filter(|line| **line == "# [input]").count(), 1);
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|line| **line == "epoch,position,vertex,rank")
+            .count(),
+        1
+    );
+
+    lines
+        .iter()
+        .filter(|line| !line.starts_with('#') && !line.starts_with("epoch"))
+        .map(|line| {
+            let fields = line.split(',').collect::<Vec<_>>();
+            let epoch = fields[0].parse().unwrap();
+            let vertex = fields[2].parse().unwrap();
+            ((epoch, vertex), fields[3].parse().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn local_hostfile_has_a_port_per_process() {
+    let path = std::env::temp_dir().join("pagerank-cluster-test.hosts");
+    cluster::write_local_hostfile(&path, 4).unwrap();
+    let hostfile = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let hosts = hostfile.lines().collect::<Vec<_>>();
+    assert_eq!(hosts.len(), 4);
+    assert_eq!(hosts.iter().collect::<HashSet<_>>().len(), 4);
+    assert!(hosts.iter().all(|host| host.starts_with("127.0.0.1:")));
+}
+
+#[test]
+fn local_processes_report_the_same_ranks() {
+    let expected = run(&["-w2"]);
+    let actual = run(&["--local-processes", "2", "-w2"]);
+
+    assert_eq!(
+        expected.keys().collect::<Vec<_>>(),
+        actual.keys().collect::<Vec<_>>()
+    );
+    for (key, rank) in &expected {
+        assert!(
+            (rank - actual[key]).abs() < 1e-8,
+            "{:?}: {} and {}",
+            key,
+            rank,
+            actual[key]
+        );
+    }
+}
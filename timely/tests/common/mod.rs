@@ -0,0 +1,280 @@
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::checkpoint::Checkpoint;
+use pagerank::convergence::StopRule;
+use pagerank::dataflow::{self, Params, State};
+use pagerank::telemetry::Summary;
+
+/// Final ranks of every vertex, per epoch.
+pub type Ranks = BTreeMap<u64, HashMap<usize, f64>>;
+
+/// Runs the PageRank dataflow over `epochs` with `workers` worker threads, feeding one epoch at a
+/// time like the examples do, and collects the ranks reported by all workers.
+pub fn run_pagerank(epochs: &[Vec<(usize, usize)>], workers: usize, stop_rule: StopRule) -> Ranks {
+    run_pagerank_from(0, epochs, workers, stop_rule)
+}
+
+/// Same as `run_pagerank`, ranking as `params` say.
+pub fn run_pagerank_with(epochs: &[Vec<(usize, usize)>], workers: usize, params: Params) -> Ranks {
+    run_pagerank_checkpointed(0, epochs, workers, params, None, None)
+}
+
+/// Same as `run_pagerank`, numbering the epochs from `first_epoch` on.
+pub fn run_pagerank_from(
+    first_epoch: u64,
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    stop_rule: StopRule,
+) -> Ranks {
+    run_pagerank_checkpointed(
+        first_epoch,
+        epochs,
+        workers,
+        Params::new(stop_rule),
+        None,
+        None,
+    )
+}
+
+/// Same as `run_pagerank_from`, saving the state of every worker to `checkpoint` after each epoch,
+/// and starting from the state saved after epoch `restore` if given.
+pub fn run_pagerank_checkpointed(
+    first_epoch: u64,
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    params: Params,
+    checkpoint: Option<Checkpoint>,
+    restore: Option<u64>,
+) -> Ranks {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let state = match (&checkpoint, restore) {
+            (Some(checkpoint), Some(epoch)) => checkpoint
+                .load(epoch, worker.index(), worker.peers())
+                .unwrap(),
+            _ => State::default(),
+        };
+        let state = Rc::new(RefCell::new(state));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = dataflow::pagerank_from(&edge_stream, params, state.clone());
+
+            let results = results.clone();
+            ranks
+                .inspect_batch(move |time, data| {
+                    for (vert, rank) in data {
+                        results.borrow_mut().push((*time, *vert, *rank));
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        input.advance_to(first_epoch);
+        for (epoch, edges) in (first_epoch..).zip(epochs.iter()) {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+
+            if let Some(checkpoint) = &checkpoint {
+                checkpoint
+                    .save(epoch, worker.index(), worker.peers(), &state.borrow())
+                    .unwrap();
+            }
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    let mut ranks = Ranks::new();
+    for result in guards.join() {
+        for (epoch, vert, rank) in result.unwrap() {
+            let previous = ranks.entry(epoch).or_default().insert(vert, rank);
+            assert!(
+                previous.is_none(),
+                "vertex {} reported twice in epoch {}",
+                vert,
+                epoch
+            );
+        }
+    }
+    ranks
+}
+
+/// Same as `run_pagerank_with`, also collecting the iterations of every epoch as worker 0 saw them.
+pub fn run_pagerank_summarized(
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    params: Params,
+) -> (Ranks, Summary) {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let summary = Rc::new(RefCell::new(Summary::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, stats) = dataflow::pagerank(&edge_stream, params);
+
+            let summary = summary.clone();
+            stats.inspect_batch(move |time, data| {
+                for stats in data {
+                    summary.borrow_mut().record(*time, *stats);
+                }
+            });
+            let results = results.clone();
+            ranks
+                .inspect_batch(move |time, data| {
+                    for (vert, rank) in data {
+                        results.borrow_mut().push((*time, *vert, *rank));
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        let results = results.borrow().clone();
+        let summary = summary.borrow().clone();
+        (results, summary)
+    })
+    .unwrap();
+
+    let mut ranks = Ranks::new();
+    let mut summaries = Vec::new();
+    for result in guards.join() {
+        let (results, summary) = result.unwrap();
+        for (epoch, vert, rank) in results {
+            ranks.entry(epoch).or_default().insert(vert, rank);
+        }
+        summaries.push(summary);
+    }
+    (ranks, summaries.remove(0))
+}
+
+/// Asserts both runs report the same vertices in every epoch, with ranks within `tolerance`.
+pub fn assert_ranks_eq(expected: &Ranks, actual: &Ranks, tolerance: f64) {
+    assert_eq!(
+        expected.keys().collect::<Vec<_>>(),
+        actual.keys().collect::<Vec<_>>(),
+        "epochs differ"
+    );
+    for (epoch, expected_ranks) in expected {
+        let actual_ranks = &actual[epoch];
+        assert_eq!(
+            expected_ranks.len(),
+            actual_ranks.len(),
+            "number of vertices differs in epoch {}",
+            epoch
+        );
+        for (vert, expected_rank) in expected_ranks {
+            let actual_rank = actual_ranks
+                .get(vert)
+                .unwrap_or_else(|| panic!("vertex {} missing in epoch {}", vert, epoch));
+            assert!(
+                (expected_rank - actual_rank).abs() <= tolerance,
+                "vertex {} in epoch {}: expected {}, got {}",
+                vert,
+                epoch,
+                expected_rank,
+                actual_rank
+            );
+        }
+    }
+}
+
+/// A deterministic citation graph of `epochs` epochs, each adding `papers` papers that cite up to
+/// `citations` earlier papers, split into one edge list per epoch.
+pub fn citation_graph(epochs: usize, papers: usize, citations: usize) -> Vec<Vec<(usize, usize)>> {
+    // a fixed linear congruential generator keeps the graph identical between runs
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move |bound: usize| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 33) as usize) % bound
+    };
+
+    let mut graph = Vec::new();
+    let mut total = 0;
+    for _ in 0..epochs {
+        let mut edges = Vec::new();
+        for paper in total..total + papers {
+            if paper > 0 {
+                for _ in 0..next(citations + 1) {
+                    edges.push((paper, next(paper)));
+                }
+            }
+        }
+        total += papers;
+        graph.push(edges);
+    }
+    graph
+}
+
+/// Normalizes `ranks` so they sum up to the number of vertices, like every solution reports them,
+/// sorted from the highest rank down.
+pub fn normalized(ranks: &HashMap<usize, f64>) -> Vec<(usize, f64)> {
+    let sum: f64 = ranks.values().sum();
+    let normalize_factor = ranks.len() as f64 / sum;
+
+    let mut rank_vec: Vec<_> = ranks
+        .iter()
+        .map(|(vert, rank)| (*vert, rank * normalize_factor))
+        .collect();
+    rank_vec.sort_by(|&(_, a), &(_, b)| b.partial_cmp(&a).unwrap());
+    rank_vec
+}
+
+/// Parses the top ranks per year printed by `networkx/pagerank.py`, such as `yearly_result.txt`.
+pub fn read_yearly_result<P: AsRef<Path>>(path: P) -> BTreeMap<u64, Vec<(usize, f64)>> {
+    let content = fs::read_to_string(path).unwrap();
+
+    let mut result = BTreeMap::new();
+    let mut year = None;
+    for line in content.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        match fields.as_slice() {
+            ["---", "year", y, "top", _, "---"] => year = Some(y.parse::<u64>().unwrap()),
+            [vert, "has", "rank:", rank] => result
+                .entry(year.expect("rank listed before its year"))
+                .or_insert(Vec::new())
+                .push((vert.parse().unwrap(), rank.parse().unwrap())),
+            [] => {}
+            _ => panic!("unexpected line: {}", line),
+        }
+    }
+    result
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pagerank::convergence::{self, Extrapolation, Gram, Norm, StopRule};
+use pagerank::dataflow::Params;
+use pagerank::reference::ReferencePageRank;
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank_with, Ranks};
+
+/// `citation_graph` with every third citation returned, so that the ranks converge geometrically
+/// rather than after as many iterations as the longest citation chain.
+fn cyclic_graph(epochs: usize, papers: usize, citations: usize) -> Vec<Vec<(usize, usize)>> {
+    citation_graph(epochs, papers, citations)
+        .into_iter()
+        .map(|edges| {
+            let returned = edges
+                .iter()
+                .step_by(3)
+                .map(|&(src, dst)| (dst, src))
+                .collect::<Vec<_>>();
+            edges.into_iter().chain(returned).collect()
+        })
+        .collect()
+}
+
+fn run_reference(epochs: &[Vec<(usize, usize)>], params: Params) -> (Ranks, u64) {
+    let mut reference = ReferencePageRank::new(params);
+    let mut ranks = BTreeMap::new();
+    let mut iterations = 0;
+    for (epoch, edges) in epochs.iter().enumerate() {
+        ranks.insert(epoch as u64, reference.advance(edges));
+        iterations += reference.iterations();
+    }
+    (ranks, iterations)
+}
+
+#[test]
+fn dataflow_matches_reference() {
+    // stopping early shows whether the same ranks were extrapolated in the same iterations. Aitken
+    // weighs the last rank of a vertex by 1 / (1 - r)^2, where r is the ratio of its last two steps,
+    // so on the vertices of this graph converging slowly, with r up to 0.98, every extrapolation
+    // blows up the rounding errors of adding up ranks in a different order a thousandfold. Two
+    // plain iterations after the last one damp them again, which is where this stops.
+    let epochs = cyclic_graph(3, 150, 4);
+    for extrapolation in [
+        Extrapolation::Aitken { period: 3 },
+        Extrapolation::Quadratic { period: 4 },
+    ] {
+        let params = Params::new(StopRule::iterations(8)).with_extrapolation(extrapolation);
+        let (expected, _) = run_reference(&epochs, params);
+        let actual = run_pagerank_with(&epochs, 3, params);
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn converges_to_the_same_ranks() {
+    let epochs = cyclic_graph(3, 150, 4);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+
+    let (expected, _) = run_reference(&epochs, params);
+    let params = params.with_extrapolation(Extrapolation::Quadratic { period: 6 });
+    let actual = run_pagerank_with(&epochs, 2, params);
+    assert_ranks_eq(&expected, &actual, 1e-8);
+}
+
+#[test]
+fn quadratic_extrapolation_saves_iterations() {
+    let epochs = cyclic_graph(4, 200, 5);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-10));
+
+    let (_, plain) = run_reference(&epochs, params);
+    let (_, extrapolated) = run_reference(
+        &epochs,
+        params.with_extrapolation(Extrapolation::Quadratic { period: 6 }),
+    );
+    assert!(
+        (extrapolated as f64) < 0.75 * plain as f64,
+        "{} iterations extrapolated, {} without",
+        extrapolated,
+        plain
+    );
+}
+
+#[test]
+fn extrapolation_is_exact_for_geometric_errors() {
+    // a rank converging to 2.0 with the error halving every iteration
+    let x = |k: i32| 2.0 + 0.5f64.powi(k);
+    assert!((convergence::aitken(x(3), x(4), x(5)) - 2.0).abs() < 1e-12);
+    // steps that do not shrink leave the rank as it is
+    assert_eq!(convergence::aitken(1.0, 2.0, 3.0), 3.0);
+
+    // two vertices converging to 1.0 and 3.0 with two eigenvectors left in the error
+    let x = |k: i32| {
+        let (a, b) = (0.8f64.powi(k), (-0.5f64).powi(k));
+        [1.0 + a + b, 3.0 + a - 2.0 * b]
+    };
+    let mut gram = Gram::default();
+    for vert in 0..2 {
+        gram.add([x(0)[vert], x(1)[vert], x(2)[vert], x(3)[vert]]);
+    }
+    let [b0, b1, b2] = gram.coefficients().unwrap();
+    for (vert, expected) in [1.0, 3.0].into_iter().enumerate() {
+        // the weights hold for any later iterations as well
+        let extrapolated = b0 * x(5)[vert] + b1 * x(6)[vert] + b2 * x(7)[vert];
+        assert!((extrapolated - expected).abs() < 1e-9);
+    }
+}
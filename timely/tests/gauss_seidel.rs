@@ -0,0 +1,68 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, Schedule};
+use pagerank::reference::ReferencePageRank;
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank_summarized, Ranks};
+
+fn run_reference(epochs: &[Vec<(usize, usize)>], params: Params) -> (Ranks, Vec<u64>) {
+    let mut reference = ReferencePageRank::new(params);
+    let mut ranks = BTreeMap::new();
+    let mut iterations = Vec::new();
+    for (epoch, edges) in epochs.iter().enumerate() {
+        ranks.insert(epoch as u64, reference.advance(edges));
+        iterations.push(reference.iterations());
+    }
+    (ranks, iterations)
+}
+
+#[test]
+fn one_worker_matches_reference() {
+    // stopping early shows whether the ranks are updated in the same order
+    let epochs = citation_graph(3, 150, 4);
+    let params = Params::new(StopRule::iterations(4)).with_schedule(Schedule::GaussSeidel);
+
+    let (expected, _) = run_reference(&epochs, params);
+    let (actual, _) = run_pagerank_summarized(&epochs, 1, params);
+    assert_ranks_eq(&expected, &actual, 1e-9);
+
+    let (jacobi, _) = run_reference(&epochs, params.with_schedule(Schedule::Jacobi));
+    assert!(expected[&0]
+        .iter()
+        .any(|(vert, rank)| (rank - jacobi[&0][vert]).abs() > 1e-6));
+}
+
+#[test]
+fn converges_to_the_same_ranks() {
+    let epochs = citation_graph(4, 200, 5);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+
+    let (expected, _) = run_reference(&epochs, params);
+    for workers in [1, 3] {
+        let (actual, _) = run_pagerank_summarized(
+            &epochs,
+            workers,
+            params.with_schedule(Schedule::GaussSeidel),
+        );
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn needs_fewer_iterations() {
+    let epochs = citation_graph(4, 200, 5);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-10));
+
+    let (_, jacobi) = run_pagerank_summarized(&epochs, 3, params);
+    let (_, gauss_seidel) =
+        run_pagerank_summarized(&epochs, 3, params.with_schedule(Schedule::GaussSeidel));
+    assert!(gauss_seidel.total_iterations() < jacobi.total_iterations());
+
+    // papers only cite older ones, so a single sweep from the newest down ranks them all when one
+    // worker holds them, and the next one changes nothing
+    let (_, iterations) = run_reference(&epochs, params.with_schedule(Schedule::GaussSeidel));
+    assert!(iterations.iter().all(|iterations| *iterations <= 2));
+}
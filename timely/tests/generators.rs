@@ -0,0 +1,47 @@
+use pagerank::generators::{self, CitationModel, Model};
+
+#[test]
+fn generators_are_deterministic() {
+    for model in [
+        Model::ErdosRenyi,
+        Model::BarabasiAlbert,
+        Model::RMat,
+        Model::Citation,
+    ] {
+        let first = generators::generator(model, 0.01, 7, 0, 1)
+            .take(3)
+            .collect::<Vec<_>>();
+        let second = generators::generator(model, 0.01, 7, 0, 1)
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(first, second, "{:?} differs between runs", model);
+        assert!(first.iter().any(|edges| !edges.is_empty()));
+    }
+}
+
+#[test]
+fn shards_add_up_to_a_single_generator() {
+    for model in [Model::ErdosRenyi, Model::RMat] {
+        let single = generators::generator(model, 0.01, 7, 0, 1).next().unwrap();
+        let sharded: usize = (0..3)
+            .map(|shard| {
+                generators::generator(model, 0.01, 7, shard, 3)
+                    .next()
+                    .unwrap()
+                    .len()
+            })
+            .sum();
+        assert_eq!(single.len(), sharded);
+    }
+}
+
+#[test]
+fn citations_only_point_to_earlier_epochs() {
+    let mut published = 0;
+    for edges in CitationModel::new(50, 4, 0.5, 7).take(5) {
+        for (src, dst) in &edges {
+            assert!(*src >= published && *dst < published);
+        }
+        published += 50;
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use std::path::Path;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::utils;
+
+use common::{normalized, read_yearly_result, run_pagerank_from};
+
+/// Checks that every year has the same top 5 as the NetworkX reference in `yearly_result`, with
+/// scores within a relative `tolerance`, when every epoch stops under `stop_rule`.
+fn assert_matches_networkx(
+    data_dir: &Path,
+    yearly_result: &Path,
+    workers: usize,
+    stop_rule: StopRule,
+    tolerance: f64,
+) {
+    let expected = read_yearly_result(yearly_result);
+    let first_year = *expected.keys().next().unwrap();
+    let last_year = *expected.keys().last().unwrap();
+
+    let epochs = (first_year..=last_year)
+        .map(|year| utils::get_citations_from_dir(data_dir, year).unwrap())
+        .collect::<Vec<_>>();
+    let actual = run_pagerank_from(first_year, &epochs, workers, stop_rule);
+
+    for (year, expected_top) in &expected {
+        let actual_top = normalized(&actual[year]);
+        let actual_top = &actual_top[..expected_top.len()];
+
+        assert_eq!(
+            expected_top
+                .iter()
+                .map(|(vert, _)| *vert)
+                .collect::<Vec<_>>(),
+            actual_top.iter().map(|(vert, _)| *vert).collect::<Vec<_>>(),
+            "top {} of year {} differ",
+            expected_top.len(),
+            year
+        );
+        for ((vert, expected_rank), (_, actual_rank)) in expected_top.iter().zip(actual_top) {
+            assert!(
+                (expected_rank - actual_rank).abs() <= tolerance * expected_rank,
+                "rank of {} in year {}: expected {}, got {}",
+                vert,
+                year,
+                expected_rank,
+                actual_rank
+            );
+        }
+    }
+}
+
+#[test]
+fn fixture_matches_networkx() {
+    // regenerate with `python3 fixture.py` in the networkx directory
+    let fixture_dir = Path::new("tests/fixtures");
+    for workers in [1, 4] {
+        assert_matches_networkx(
+            &fixture_dir.join("incremental"),
+            &fixture_dir.join("yearly_result.txt"),
+            workers,
+            StopRule::tolerance(Norm::LInf, 1e-12).with_max_iterations(1000),
+            1e-8,
+        );
+    }
+}
+
+#[test]
+fn fixture_matches_networkx_under_its_criterion() {
+    // the fixture was ranked with `tol=1e-12`, which is the tolerance of `Norm::ScaledL1`
+    let fixture_dir = Path::new("tests/fixtures");
+    assert_matches_networkx(
+        &fixture_dir.join("incremental"),
+        &fixture_dir.join("yearly_result.txt"),
+        2,
+        StopRule::tolerance(Norm::ScaledL1, 1e-12),
+        1e-8,
+    );
+}
+
+#[test]
+#[ignore = "needs the partitioned HepPh dataset in ../dataset/incremental, see dataset/README.md"]
+fn hep_ph_matches_networkx() {
+    assert_matches_networkx(
+        Path::new("../dataset/incremental"),
+        Path::new("../networkx/yearly_result.txt"),
+        4,
+        StopRule::tolerance(Norm::LInf, 1e-12).with_max_iterations(1000),
+        1e-6,
+    );
+}
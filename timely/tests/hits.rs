@@ -0,0 +1,170 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::WarmStart;
+use pagerank::hits;
+
+use common::{assert_ranks_eq, citation_graph, normalized, Ranks};
+
+/// Runs `hits::hits` over `epochs` with `workers` worker threads until `stop_rule` holds, and
+/// collects the authority and hub scores of every epoch, normalized to sum up to the number of
+/// vertices.
+fn run_hits(
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    stop_rule: StopRule,
+    warm_start: WarmStart,
+) -> (Ranks, Ranks) {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (authorities, hubs, _stats) = hits::hits(&edge_stream, stop_rule, warm_start);
+
+            for (is_hub, scores) in [(false, authorities), (true, hubs)] {
+                let results = results.clone();
+                scores
+                    .inspect_batch(move |time, data| {
+                        for (vert, score) in data {
+                            results.borrow_mut().push((is_hub, *time, *vert, *score));
+                        }
+                    })
+                    .probe_with(&mut probe);
+            }
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    let (mut authorities, mut hubs) = (Ranks::new(), Ranks::new());
+    for result in guards.join() {
+        for (is_hub, epoch, vert, score) in result.unwrap() {
+            let scores = if is_hub { &mut hubs } else { &mut authorities };
+            scores.entry(epoch).or_default().insert(vert, score);
+        }
+    }
+    (normalize_epochs(&authorities), normalize_epochs(&hubs))
+}
+
+/// Power iteration over the graph of every epoch until the scores no longer change, dividing by the
+/// sum of the previous scores like the dataflow does.
+fn sequential_hits(epochs: &[Vec<(usize, usize)>]) -> (Ranks, Ranks) {
+    let (mut authorities, mut hubs) = (Ranks::new(), Ranks::new());
+    let mut edges = Vec::new();
+    for (epoch, batch) in epochs.iter().enumerate() {
+        edges.extend(batch.iter().copied());
+        let vertices = edges
+            .iter()
+            .flat_map(|&(src, dst)| [src, dst])
+            .collect::<HashSet<_>>();
+
+        let mut authority: HashMap<usize, f64> = vertices.iter().map(|v| (*v, 1.0)).collect();
+        let mut hub = authority.clone();
+        for _ in 0..2000 {
+            let authority_sum: f64 = authority.values().sum();
+            let hub_sum: f64 = hub.values().sum();
+            let mut new_authority: HashMap<usize, f64> =
+                vertices.iter().map(|v| (*v, 0.0)).collect();
+            let mut new_hub = new_authority.clone();
+            for &(src, dst) in &edges {
+                *new_authority.get_mut(&dst).unwrap() += hub[&src] / hub_sum;
+                *new_hub.get_mut(&src).unwrap() += authority[&dst] / authority_sum;
+            }
+            authority = new_authority;
+            hub = new_hub;
+        }
+
+        authorities.insert(epoch as u64, authority);
+        hubs.insert(epoch as u64, hub);
+    }
+    (normalize_epochs(&authorities), normalize_epochs(&hubs))
+}
+
+/// Runs until the scores no longer change, like `sequential_hits`.
+fn converged_hits(epochs: &[Vec<(usize, usize)>], workers: usize) -> (Ranks, Ranks) {
+    let stop_rule = StopRule::tolerance(Norm::Relative, 1e-14).with_max_iterations(2000);
+    run_hits(epochs, workers, stop_rule, WarmStart::Previous)
+}
+
+fn normalize_epochs(scores: &Ranks) -> Ranks {
+    scores
+        .iter()
+        .map(|(epoch, scores)| (*epoch, normalized(scores).into_iter().collect()))
+        .collect()
+}
+
+#[test]
+fn authorities_and_hubs_of_a_bipartite_graph() {
+    // 0 and 1 only cite, 2 and 3 are only cited, with 2 cited more often
+    let epochs = vec![vec![(0, 2), (0, 3), (1, 2)]];
+    let (authorities, hubs) = converged_hits(&epochs, 2);
+
+    // the authorities are the principal eigenvector of [[2, 1], [1, 1]], (1, (sqrt(5) - 1) / 2)
+    let ratio = authorities[&0][&3] / authorities[&0][&2];
+    assert!(
+        (ratio - (5f64.sqrt() - 1.0) / 2.0).abs() < 1e-9,
+        "{}",
+        ratio
+    );
+    assert_eq!(authorities[&0][&0], 0.0);
+    assert_eq!(hubs[&0][&2], 0.0);
+    assert!(hubs[&0][&0] > hubs[&0][&1]);
+}
+
+#[test]
+fn same_scores_as_sequential_power_iteration() {
+    let epochs = citation_graph(3, 30, 4);
+    let (expected_authorities, expected_hubs) = sequential_hits(&epochs);
+
+    for workers in [1, 3] {
+        let (authorities, hubs) = converged_hits(&epochs, workers);
+        assert_ranks_eq(&expected_authorities, &authorities, 1e-6);
+        assert_ranks_eq(&expected_hubs, &hubs, 1e-6);
+    }
+}
+
+#[test]
+fn workers_without_new_links_take_part_in_the_epoch() {
+    // the second epoch links even vertices only, so half of the workers get nothing new, yet their
+    // scores count towards the sums that scale the first iteration
+    let epochs = vec![
+        vec![(0, 1), (1, 2), (2, 3), (3, 0), (5, 1), (7, 3)],
+        vec![(4, 0), (6, 2), (4, 2)],
+        vec![(8, 4), (9, 5)],
+    ];
+    for warm_start in [WarmStart::Previous, WarmStart::Cold] {
+        let expected = run_hits(&epochs, 1, StopRule::iterations(7), warm_start);
+        for workers in [2, 4] {
+            let (authorities, hubs) =
+                run_hits(&epochs, workers, StopRule::iterations(7), warm_start);
+            assert_ranks_eq(&expected.0, &authorities, 1e-12);
+            assert_ranks_eq(&expected.1, &hubs, 1e-12);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::fs;
+use std::path::PathBuf;
+
+use pagerank::utils;
+
+/// A directory of its own for every test, holding `<year>-edges.txt` with `lines`.
+fn data_dir(name: &str, year: u64, lines: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pagerank-input-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(format!("{}-edges.txt", year)), lines).unwrap();
+    dir
+}
+
+#[test]
+fn blank_lines_are_skipped() {
+    let dir = data_dir("blank", 1992, "1 0\n\n  \n2 1 extra\n3 2\n");
+    assert_eq!(
+        utils::get_citations_from_dir(&dir, 1992).unwrap(),
+        [(1, 0), (2, 1), (3, 2)]
+    );
+    // the blank lines still count when the lines are shared out
+    assert_eq!(
+        utils::get_citation_shard_from_dir(&dir, 1992, 1, 2).unwrap(),
+        [(2, 1)]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn malformed_lines_fail_with_their_place() {
+    let dir = data_dir("malformed", 1992, "1 0\n2 x\n");
+    let err = utils::get_citations_from_dir(&dir, 1992).unwrap_err();
+    assert!(err.to_string().contains("1992-edges.txt:2:"), "{}", err);
+
+    let dir = data_dir("single", 1993, "1 0\n\n3\n");
+    let err = utils::get_citations_from_dir(&dir, 1993).unwrap_err();
+    assert!(err.to_string().contains("1993-edges.txt:3:"), "{}", err);
+
+    // a missing year fails rather than being left out
+    assert!(utils::get_years_from_dir(&dir, 1993, 1993, 1994).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_dir_all(std::env::temp_dir().join("pagerank-input-malformed")).unwrap();
+}
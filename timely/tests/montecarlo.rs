@@ -0,0 +1,124 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::Params;
+use pagerank::montecarlo::{self, Estimate, Z_95};
+use pagerank::reference::ReferencePageRank;
+
+use common::citation_graph;
+
+/// Estimates of every vertex, per epoch.
+type Estimates = BTreeMap<u64, HashMap<usize, Estimate>>;
+
+/// Runs `montecarlo::montecarlo` over `epochs` with `workers` worker threads, and collects the
+/// estimates reported by all workers.
+fn run_montecarlo(
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    walks: usize,
+    seed: u64,
+) -> Estimates {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (estimates, _stats) = montecarlo::montecarlo(&edge_stream, walks, 0.85, seed);
+
+            let results = results.clone();
+            estimates
+                .inspect_batch(move |time, data| {
+                    for (vert, estimate) in data {
+                        results.borrow_mut().push((*time, *vert, *estimate));
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    let mut estimates = Estimates::new();
+    for result in guards.join() {
+        for (epoch, vert, estimate) in result.unwrap() {
+            let previous = estimates.entry(epoch).or_default().insert(vert, estimate);
+            assert!(previous.is_none(), "vertex {} reported twice", vert);
+        }
+    }
+    estimates
+}
+
+#[test]
+fn same_estimates_with_any_number_of_workers() {
+    let epochs = citation_graph(3, 80, 4);
+    let expected = run_montecarlo(&epochs, 1, 20, 3);
+    let actual = run_montecarlo(&epochs, 3, 20, 3);
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn estimates_are_close_to_reference() {
+    let epochs = citation_graph(3, 60, 4);
+    let estimates = run_montecarlo(&epochs, 2, 400, 0);
+
+    let mut reference = ReferencePageRank::new(Params::new(StopRule::tolerance(Norm::L1, 1e-12)));
+    for (epoch, edges) in epochs.iter().enumerate() {
+        let ranks = reference.advance(edges);
+        let estimates = &estimates[&(epoch as u64)];
+        assert_eq!(ranks.len(), estimates.len());
+
+        // walks kept from earlier epochs estimate as well as new ones would
+        let mut within = 0;
+        for (vert, rank) in &ranks {
+            let estimate = estimates[vert];
+            let deviation = (estimate.upper - estimate.rank) / Z_95;
+            assert!(
+                (estimate.rank - rank).abs() <= 5.0 * deviation + 1e-12,
+                "vertex {} in epoch {}: expected {}, estimated {:?}",
+                vert,
+                epoch,
+                rank,
+                estimate
+            );
+            within += usize::from(estimate.lower <= *rank && *rank <= estimate.upper);
+        }
+        assert!(within as f64 >= 0.8 * ranks.len() as f64);
+    }
+}
+
+#[test]
+fn own_walks_are_certain() {
+    // a vertex nobody cites is only visited by its own walks
+    let estimate = Estimate::from_visits(50, 50, 0.85);
+    assert!((estimate.rank - 0.15).abs() < 1e-12);
+    assert_eq!(estimate.lower, estimate.rank);
+    assert_eq!(estimate.upper, estimate.rank);
+
+    let estimate = Estimate::from_visits(150, 50, 0.85);
+    assert!(estimate.lower < estimate.rank && estimate.rank < estimate.upper);
+}
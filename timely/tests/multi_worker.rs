@@ -0,0 +1,63 @@
+mod common;
+
+use pagerank::convergence::{Norm, StopRule};
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank};
+
+const WORKERS: [usize; 3] = [2, 4, 8];
+
+fn assert_same_for_all_workers(
+    epochs: &[Vec<(usize, usize)>],
+    stop_rule: StopRule,
+    tolerance: f64,
+) {
+    let expected = run_pagerank(epochs, 1, stop_rule);
+    for workers in WORKERS {
+        let actual = run_pagerank(epochs, workers, stop_rule);
+        assert_ranks_eq(&expected, &actual, tolerance);
+    }
+}
+
+#[test]
+fn chain_and_cycle() {
+    let epochs = vec![
+        vec![(1, 0), (2, 1), (3, 2)],
+        vec![(4, 3), (0, 4), (5, 0), (5, 2)],
+    ];
+    assert_same_for_all_workers(&epochs, StopRule::iterations(20), 1e-12);
+}
+
+#[test]
+fn dangling_vertices_seen_only_as_dst() {
+    // 10 to 17 are never a `src`, so they are only known from the edges citing them
+    let epochs = vec![
+        (0..8).map(|src| (src, src + 10)).collect(),
+        (0..8).map(|src| (src, 17 - src)).collect(),
+    ];
+    let ranks = run_pagerank(&epochs, 4, StopRule::iterations(5));
+    for epoch_ranks in ranks.values() {
+        assert_eq!(epoch_ranks.len(), 16);
+    }
+    assert_same_for_all_workers(&epochs, StopRule::iterations(5), 1e-12);
+}
+
+#[test]
+fn citation_graph_fixed_iterations() {
+    let epochs = citation_graph(4, 200, 5);
+    assert_same_for_all_workers(&epochs, StopRule::iterations(30), 1e-9);
+}
+
+#[test]
+fn citation_graph_tolerance() {
+    // every worker count must stop after the same iteration, as they agree on the global residual
+    let epochs = citation_graph(4, 200, 5);
+    for norm in [
+        Norm::L1,
+        Norm::L2,
+        Norm::LInf,
+        Norm::Relative,
+        Norm::ScaledL1,
+    ] {
+        assert_same_for_all_workers(&epochs, StopRule::tolerance(norm, 1e-10), 1e-8);
+    }
+}
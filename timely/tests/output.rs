@@ -0,0 +1,86 @@
+use std::fs;
+use std::process::Command;
+
+use serde_json::Value;
+
+const DATA_DIR: &str = "tests/fixtures/incremental";
+
+/// Runs the `pagerank` binary for `measure` with JSON output, and parses every line it printed,
+/// the config included.
+fn run_json(measure: &str, extra_args: &[&str]) -> Vec<Value> {
+    let output = Command::new(env!("CARGO_BIN_EXE_pagerank"))
+        .args([
+            "iterations",
+            "--iterations",
+            "10",
+            "--data-dir",
+            DATA_DIR,
+            "--end",
+            "1995",
+            "--measure",
+            measure,
+            "--format",
+            "json",
+        ])
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.is_empty())
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|err| panic!("{}: {}", err, line)))
+        .collect()
+}
+
+#[test]
+fn every_measure_prints_valid_json() {
+    // hits prints the authorities and the hubs of every epoch
+    for (measure, key, lines_per_epoch) in [
+        ("pagerank", "top", 1),
+        ("hits", "top", 2),
+        ("2d", "top_2d", 1),
+        ("montecarlo", "top", 1),
+    ] {
+        let lines = run_json(measure, &[]);
+        let epochs = lines
+            .iter()
+            .filter(|line| line.get("config").is_none())
+            .collect::<Vec<_>>();
+        assert_eq!(epochs.len(), 4 * lines_per_epoch, "{}", measure);
+        for epoch in epochs {
+            assert!(epoch[key].is_array(), "{}: {}", measure, epoch);
+        }
+    }
+}
+
+#[test]
+fn scores_without_a_finite_value_are_null() {
+    // no vertex is trusted before 1995, so nothing has a trustrank and every ratio is infinite
+    let seeds = std::env::temp_dir().join("pagerank-output-trusted.txt");
+    fs::write(&seeds, "9500000\n").unwrap();
+    let epochs = run_json(
+        "trustrank",
+        &[
+            "--trusted",
+            seeds.to_str().unwrap(),
+            "--anomaly-ratio",
+            "1e-9",
+        ],
+    );
+    fs::remove_file(&seeds).unwrap();
+
+    let anomalies = epochs
+        .iter()
+        .filter_map(|epoch| epoch["anomalies"].as_array())
+        .flatten()
+        .collect::<Vec<_>>();
+    assert!(!anomalies.is_empty());
+    assert!(anomalies.iter().any(|anomaly| anomaly["ratio"].is_null()));
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Centrality, Decay, Params, WarmStart};
+use pagerank::reference::{Deviation, ReferencePageRank};
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank, run_pagerank_with, Ranks};
+
+fn run_reference(epochs: &[Vec<(usize, usize)>], stop_rule: StopRule) -> Ranks {
+    run_reference_with(epochs, Params::new(stop_rule))
+}
+
+fn run_reference_with(epochs: &[Vec<(usize, usize)>], params: Params) -> Ranks {
+    let mut reference = ReferencePageRank::new(params);
+    let mut ranks = BTreeMap::new();
+    for (epoch, edges) in epochs.iter().enumerate() {
+        ranks.insert(epoch as u64, reference.advance(edges));
+    }
+    ranks
+}
+
+#[test]
+fn dataflow_matches_reference() {
+    let epochs = citation_graph(4, 200, 5);
+    for stop_rule in [
+        StopRule::iterations(25),
+        StopRule::tolerance(Norm::L1, 1e-10),
+        StopRule::tolerance(Norm::Relative, 1e-12).with_max_iterations(15),
+    ] {
+        let expected = run_reference(&epochs, stop_rule);
+        let actual = run_pagerank(&epochs, 4, stop_rule);
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn warm_starts_match_reference() {
+    // stopping early shows where new vertices started from, whatever the number of workers
+    let epochs = citation_graph(4, 200, 5);
+    for warm_start in [WarmStart::Cold, WarmStart::Mean, WarmStart::Teleport] {
+        let params = Params::new(StopRule::iterations(6)).with_warm_start(warm_start);
+        let expected = run_reference_with(&epochs, params);
+        let actual = run_pagerank_with(&epochs, 4, params);
+        assert_ranks_eq(&expected, &actual, 1e-9);
+    }
+}
+
+#[test]
+fn deviation_of_identical_ranks() {
+    let epochs = citation_graph(1, 100, 3);
+    let ranks = run_reference(&epochs, StopRule::iterations(10));
+    let deviation = Deviation::between(&ranks[&0], &ranks[&0], 5);
+    assert_eq!(deviation.max, 0.0);
+    assert_eq!(deviation.top_k_overlap, 1.0);
+}
+
+#[test]
+fn other_centralities_match_reference() {
+    let epochs = citation_graph(4, 200, 5);
+    for centrality in [
+        Centrality::Katz {
+            alpha: 0.2,
+            beta: 1.0,
+        },
+        Centrality::Eigenvector,
+        Centrality::CiteRank { tau: 1.5 },
+    ] {
+        let params =
+            Params::new(StopRule::tolerance(Norm::Relative, 1e-12).with_max_iterations(40))
+                .with_centrality(centrality);
+        let expected = run_reference_with(&epochs, params);
+        let actual = run_pagerank_with(&epochs, 4, params);
+        assert_ranks_eq(&expected, &actual, 1e-8);
+    }
+}
+
+#[test]
+fn katz_of_a_citation_chain() {
+    // 0 and 2 cite 1, which cites 3, and nothing cites 0 or 2
+    let epochs = vec![vec![(0, 1), (2, 1), (1, 3)]];
+    let params =
+        Params::new(StopRule::tolerance(Norm::L1, 1e-12)).with_centrality(Centrality::Katz {
+            alpha: 0.1,
+            beta: 1.0,
+        });
+    let ranks = &run_pagerank_with(&epochs, 2, params)[&0];
+    for (vert, rank) in [(0, 1.0), (1, 1.2), (2, 1.0), (3, 1.12)] {
+        assert!(
+            (ranks[&vert] - rank).abs() < 1e-12,
+            "{}: {}",
+            vert,
+            ranks[&vert]
+        );
+    }
+}
+
+#[test]
+fn citerank_favours_recent_citations() {
+    // 1 is cited in the first epoch, 3 the same way in the second
+    let epochs = vec![vec![(0, 1)], vec![(2, 3)]];
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-12);
+    let params = Params::new(stop_rule).with_centrality(Centrality::CiteRank { tau: 1.0 });
+    let ranks = &run_pagerank_with(&epochs, 2, params)[&1];
+
+    // the teleport value of 0 and 1 decayed by one epoch, that of 2 and 3 not yet
+    let decayed = 0.15 * (-1.0f64).exp();
+    for (vert, rank) in [
+        (0, decayed),
+        (1, decayed + 0.85 * decayed),
+        (2, 0.15),
+        (3, 0.15 + 0.85 * 0.15),
+    ] {
+        assert!(
+            (ranks[&vert] - rank).abs() < 1e-12,
+            "{}: {}",
+            vert,
+            ranks[&vert]
+        );
+    }
+
+    // plain PageRank cannot tell them apart
+    let ranks = &run_pagerank(&epochs, 2, stop_rule)[&1];
+    assert!((ranks[&1] - ranks[&3]).abs() < 1e-12);
+}
+
+#[test]
+fn decayed_citations_match_reference() {
+    let epochs = citation_graph(5, 150, 4);
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-10);
+    for decay in [
+        Decay::Exponential { tau: 1.5 },
+        Decay::Power { exponent: 2.0 },
+        Decay::Window { epochs: 2 },
+    ] {
+        for centrality in [
+            Centrality::PageRank,
+            Centrality::Katz {
+                alpha: 0.2,
+                beta: 1.0,
+            },
+        ] {
+            let params = Params::new(stop_rule)
+                .with_centrality(centrality)
+                .with_decay(decay);
+            let expected = run_reference_with(&epochs, params);
+            let actual = run_pagerank_with(&epochs, 3, params);
+            assert_ranks_eq(&expected, &actual, 1e-8);
+        }
+    }
+}
+
+#[test]
+fn older_citations_weigh_less() {
+    // 0 cites 1 in the first epoch and 2 in the second
+    let epochs = vec![vec![(0, 1)], vec![(0, 2)]];
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12))
+        .with_decay(Decay::Exponential { tau: 1.0 });
+    let ranks = &run_pagerank_with(&epochs, 2, params)[&1];
+
+    // 0 splits its rank of 0.15 by the weights 1/e and 1
+    let share = 0.15 / (1.0 + (-1.0f64).exp());
+    assert!((ranks[&1] - (0.15 + 0.85 * share * (-1.0f64).exp())).abs() < 1e-12);
+    assert!((ranks[&2] - (0.15 + 0.85 * share)).abs() < 1e-12);
+
+    // a window of one epoch forgets the citation of 1
+    let params = params.with_decay(Decay::Window { epochs: 1 });
+    let ranks = &run_pagerank_with(&epochs, 2, params)[&1];
+    assert!((ranks[&1] - 0.15).abs() < 1e-12);
+    assert!((ranks[&2] - (0.15 + 0.85 * 0.15)).abs() < 1e-12);
+}
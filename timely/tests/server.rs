@@ -0,0 +1,183 @@
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{self, Params, Teleport};
+use pagerank::server::{self, RankStore};
+use pagerank::trustrank;
+
+use common::{citation_graph, normalized, run_pagerank};
+
+fn answer(store: &RankStore, query: &str) -> Value {
+    serde_json::from_str(&store.answer(query)).unwrap()
+}
+
+#[test]
+fn answers_from_the_latest_epoch() {
+    let store = RankStore::new();
+    assert!(answer(&store, "rank 1")["error"]
+        .as_str()
+        .unwrap()
+        .contains("no epoch"));
+
+    // normalized to sum up to the number of vertices
+    store.publish(1992, vec![(0, 1.0), (1, 3.0)]);
+    store.publish(1993, vec![(0, 1.0), (1, 1.0), (2, 2.0)]);
+    assert_eq!(store.latest_epoch(), Some(1993));
+
+    assert_eq!(
+        answer(&store, "rank 2"),
+        json!({"epoch": 1993, "vertex": 2, "rank": 1.5, "percentile": 200.0 / 3.0})
+    );
+    let top = answer(&store, "top 2");
+    assert_eq!(top["epoch"], 1993);
+    assert_eq!(top["top"][0], json!({"vertex": 2, "rank": 1.5}));
+    assert_eq!(top["top"].as_array().unwrap().len(), 2);
+    assert_eq!(answer(&store, "top 5")["top"].as_array().unwrap().len(), 3);
+    assert_eq!(
+        answer(&store, "history 1"),
+        json!({"vertex": 1, "history": [{"epoch": 1992, "rank": 1.5}, {"epoch": 1993, "rank": 0.75}]})
+    );
+    assert_eq!(
+        answer(&store, "history 2")["history"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    assert!(answer(&store, "rank 7")["error"]
+        .as_str()
+        .unwrap()
+        .contains("not in epoch 1993"));
+    assert!(answer(&store, "rank x")["error"]
+        .as_str()
+        .unwrap()
+        .contains("invalid vertex"));
+    assert!(answer(&store, "percentile 1")["error"]
+        .as_str()
+        .unwrap()
+        .contains("unknown query"));
+}
+
+#[test]
+fn publishes_the_ranks_of_every_epoch() {
+    let epochs = citation_graph(4, 40, 3);
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-10);
+    let expected = run_pagerank(&epochs, 1, stop_rule);
+
+    let store = RankStore::new();
+    let graph = Arc::new(epochs);
+    let published = store.clone();
+    timely::execute(Config::process(2), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let store = published.clone();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = dataflow::pagerank(&edge_stream, Params::new(stop_rule));
+            server::publish_ranks(&ranks, store).probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in graph.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+
+            // an epoch is there to query as soon as its ranks are out
+            if worker.index() == 0 {
+                assert_eq!(published.latest_epoch(), Some(epoch as u64));
+            }
+        }
+    })
+    .unwrap();
+
+    for (epoch, ranks) in &expected {
+        for (vertex, rank) in normalized(ranks) {
+            let history = answer(&store, &format!("history {}", vertex));
+            let published = history["history"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|entry| entry["epoch"] == json!(epoch))
+                .unwrap()["rank"]
+                .as_f64()
+                .unwrap();
+            assert!((published - rank).abs() < 1e-8, "{}: {}", vertex, rank);
+        }
+    }
+}
+
+#[test]
+fn publishes_trustrank_before_any_seed_appears() {
+    // nothing is trusted in the first epoch, whose citations form no cycle, so its ranks all drop
+    // to zero within a few iterations
+    let epochs = Arc::new(vec![vec![(1, 0), (2, 1)], vec![(3, 2), (3, 0)]]);
+    let params = Params::new(StopRule::iterations(5));
+
+    let store = RankStore::new();
+    let published = store.clone();
+    timely::execute(Config::process(2), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let store = published.clone();
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = trustrank::trustrank(&edge_stream, params, Teleport::seeds([3]));
+            server::publish_ranks(&ranks, store).probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+    })
+    .unwrap();
+
+    let history = answer(&store, "history 0")["history"].clone();
+    assert_eq!(history[0], json!({"epoch": 0, "rank": 0.0}));
+    assert!(history[1]["rank"].as_f64().unwrap() > 0.0);
+
+    let top = answer(&store, "top 4");
+    assert_eq!(top["epoch"], 1);
+    assert_eq!(top["top"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn answers_queries_over_tcp() {
+    let store = RankStore::new();
+    store.publish(0, vec![(9303255, 2.0), (9304001, 1.0)]);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let served = store.clone();
+    std::thread::spawn(move || server::serve(listener, served));
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    for query in ["rank 9303255", "top 1", "history 9304001"] {
+        writeln!(stream, "{}", query).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), store.answer(query));
+    }
+}
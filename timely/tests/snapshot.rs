@@ -0,0 +1,38 @@
+mod common;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::utils;
+
+use common::{assert_ranks_eq, citation_graph, run_pagerank, run_pagerank_from};
+
+#[test]
+fn epochs_before_start_form_a_snapshot() {
+    let batches = vec![vec![(1, 0)], vec![(2, 1)], vec![(3, 2)], vec![(4, 3)]];
+
+    let epochs = utils::start_at(batches.clone(), 1992, 1994).collect::<Vec<_>>();
+    assert_eq!(
+        epochs,
+        [(1994, vec![(1, 0), (2, 1), (3, 2)]), (1995, vec![(4, 3)])]
+    );
+
+    // without a snapshot, the epochs are only numbered
+    let epochs = utils::start_at(batches, 1998, 1998).collect::<Vec<_>>();
+    assert_eq!(epochs[0], (1998, vec![(1, 0)]));
+    assert_eq!(epochs.len(), 4);
+}
+
+#[test]
+fn snapshot_converges_to_incremental_ranks() {
+    let epochs = citation_graph(5, 60, 4);
+    let stop_rule = StopRule::tolerance(Norm::L1, 1e-12).with_max_iterations(1000);
+
+    let mut expected = run_pagerank(&epochs, 2, stop_rule);
+    expected.retain(|epoch, _| *epoch >= 3);
+
+    let snapshot = utils::start_at(epochs, 0, 3)
+        .map(|(_, edges)| edges)
+        .collect::<Vec<_>>();
+    let actual = run_pagerank_from(3, &snapshot, 2, stop_rule);
+
+    assert_ranks_eq(&expected, &actual, 1e-8);
+}
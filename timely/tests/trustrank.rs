@@ -0,0 +1,138 @@
+mod common;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use timely::dataflow::operators::*;
+use timely::dataflow::{InputHandle, ProbeHandle};
+use timely::Config;
+
+use pagerank::convergence::{Norm, StopRule};
+use pagerank::dataflow::{Params, Teleport};
+use pagerank::reference::ReferencePageRank;
+use pagerank::trustrank;
+
+use common::{assert_ranks_eq, citation_graph, Ranks};
+
+/// Runs `trustrank::trustrank`, or `trustrank::anti_trustrank` if `anti`, from `seeds` over
+/// `epochs` with `workers` worker threads, and collects the ranks reported by all workers.
+fn run_trustrank(
+    epochs: &[Vec<(usize, usize)>],
+    workers: usize,
+    params: Params,
+    seeds: Teleport,
+    anti: bool,
+) -> Ranks {
+    let epochs = Arc::new(epochs.to_vec());
+
+    let guards = timely::execute(Config::process(workers), move |worker| {
+        let mut input = InputHandle::new();
+        let mut probe = ProbeHandle::new();
+        let results = Rc::new(RefCell::new(Vec::new()));
+
+        worker.dataflow::<u64, _, _>(|scope| {
+            let edge_stream = input.to_stream(scope);
+            let (ranks, _stats) = if anti {
+                trustrank::anti_trustrank(&edge_stream, params, seeds.clone())
+            } else {
+                trustrank::trustrank(&edge_stream, params, seeds.clone())
+            };
+
+            let results = results.clone();
+            ranks
+                .inspect_batch(move |time, data| {
+                    for (vert, rank) in data {
+                        results.borrow_mut().push((*time, *vert, *rank));
+                    }
+                })
+                .probe_with(&mut probe);
+        });
+
+        for (epoch, edges) in epochs.iter().enumerate() {
+            if worker.index() == 0 {
+                for edge in edges {
+                    input.send(*edge);
+                }
+            }
+            input.advance_to(epoch as u64 + 1);
+            worker.step_while(|| probe.less_than(input.time()));
+        }
+
+        let results = results.borrow().clone();
+        results
+    })
+    .unwrap();
+
+    let mut ranks = Ranks::new();
+    for result in guards.join() {
+        for (epoch, vert, rank) in result.unwrap() {
+            ranks.entry(epoch).or_default().insert(vert, rank);
+        }
+    }
+    ranks
+}
+
+fn run_reference(epochs: &[Vec<(usize, usize)>], params: Params, seeds: Teleport) -> Ranks {
+    let mut reference = ReferencePageRank::new(params).with_teleport(seeds);
+    let mut ranks = BTreeMap::new();
+    for (epoch, edges) in epochs.iter().enumerate() {
+        ranks.insert(epoch as u64, reference.advance(edges));
+    }
+    ranks
+}
+
+#[test]
+fn trustrank_matches_reference() {
+    let epochs = citation_graph(3, 150, 4);
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+    // seeds from every epoch, some of which are not there yet in the first ones
+    let seeds = Teleport::seeds([3, 40, 170, 420]);
+
+    let expected = run_reference(&epochs, params, seeds.clone());
+    let actual = run_trustrank(&epochs, 3, params, seeds, false);
+    assert_ranks_eq(&expected, &actual, 1e-9);
+}
+
+#[test]
+fn anti_trustrank_is_trustrank_of_the_transposed_graph() {
+    let epochs = citation_graph(3, 120, 4);
+    let transposed = epochs
+        .iter()
+        .map(|edges| edges.iter().map(|&(src, dst)| (dst, src)).collect())
+        .collect::<Vec<_>>();
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+    let seeds = Teleport::seeds([7, 95, 300]);
+
+    let expected = run_reference(&transposed, params, seeds.clone());
+    let actual = run_trustrank(&epochs, 2, params, seeds, true);
+    assert_ranks_eq(&expected, &actual, 1e-9);
+}
+
+#[test]
+fn citation_ring_is_an_anomaly() {
+    // the trusted 0 and 1 cite 2 and 3, while 10 to 14 cite each other in a ring and all cite 15
+    let mut edges = vec![(0, 2), (0, 3), (1, 2), (1, 3), (2, 3), (3, 4)];
+    for vert in 10..15 {
+        edges.push((vert, 10 + (vert + 1 - 10) % 5));
+        edges.push((vert, 15));
+    }
+    let params = Params::new(StopRule::tolerance(Norm::L1, 1e-12));
+    let ranks = ReferencePageRank::new(params).advance(&edges);
+    let trustranks = ReferencePageRank::new(params)
+        .with_teleport(Teleport::seeds([0, 1]))
+        .advance(&edges);
+
+    let ranks = ranks.into_iter().collect::<Vec<_>>();
+    let trustranks = trustranks.into_iter().collect::<Vec<_>>();
+    let anomalies = trustrank::anomalies(&ranks, &trustranks, 10.0);
+
+    // the trusted seeds never reach the ring, which 15 gains the most from, and whose TrustRank is
+    // only what is left of where it started from
+    assert_eq!(anomalies[0].vertex, 15);
+    assert!(anomalies
+        .iter()
+        .all(|anomaly| (10..16).contains(&anomaly.vertex) && anomaly.ratio > 1e6));
+    assert_eq!(anomalies.len(), 6);
+}